@@ -20,6 +20,17 @@ export default {
     // Game running state.
     running: null,
 
+    // Timed round state, and the time it was received at.
+    round: null,
+    roundTime: null,
+
+    // Final scoreboard, once the round has finished.
+    finalScoreboard: null,
+
+    // Announcements, oldest first, and IDs of dismissed persistent announcements.
+    announcements: [],
+    dismissedAnnouncements: JSON.parse(localStorage.getItem('announcements.dismissed') || '[]'),
+
     // Game state, if available.
     game: null,
 
@@ -41,6 +52,49 @@ export default {
 
         // Register message listeners
         this.socket.addListener('game_state', (running) => this.running = running);
+        this.socket.addListener('round', (round) => {
+            this.round = round;
+            this.roundTime = Date.now();
+            if(round.phase !== 'finished')
+                this.finalScoreboard = null;
+        });
+        this.socket.addListener('final_scoreboard', (scoreboard) => this.finalScoreboard = scoreboard);
+        this.socket.addListener('announcements', (announcements) => this.announcements = announcements);
+        this.socket.addListener('announcement', (announcement) => this.onAnnouncement(announcement));
+        this.socket.addListener('sanction', (record) => this.onSanction(record));
+    },
+
+    // Handle sanction or bonus applied to our team.
+    onSanction(record) {
+        let titles = {
+            freeze: 'Your team is frozen',
+            unfreeze: 'Your team is unfrozen',
+            fine: 'Your team is fined',
+            drain: 'Your team is drained of energy',
+            bonus: 'Your team received a bonus',
+        };
+        let variant = record.sanction.kind == 'bonus' || record.sanction.kind == 'unfreeze' ? 'success' : 'danger';
+        this.toast(record.reason, variant, titles[record.sanction.kind] || 'Referee');
+    },
+
+    // Handle new announcement.
+    onAnnouncement(announcement) {
+        this.announcements.push(announcement);
+
+        // Persistent announcements are shown until dismissed, toast others
+        if(!announcement.persistent)
+            this.toast(announcement.text, severityVariant(announcement.severity), 'Announcement');
+    },
+
+    // Persistent announcements that are not dismissed.
+    persistentAnnouncements() {
+        return this.announcements.filter((a) => a.persistent && !this.dismissedAnnouncements.includes(a.id));
+    },
+
+    // Dismiss a persistent announcement.
+    dismissAnnouncement(id) {
+        this.dismissedAnnouncements.push(id);
+        localStorage.setItem('announcements.dismissed', JSON.stringify(this.dismissedAnnouncements));
     },
 
     // Initialize game.
@@ -58,13 +112,25 @@ export default {
     },
 
     // Show toast notification.
-    toast(msg) {
+    toast(msg, variant = 'warning', title = 'Notification') {
         this.vueContext.$bvToast.toast(msg, {
-            title: 'Notification',
+            title,
             autoHideDelay: 3000,
-            variant: 'warning',
+            variant,
             solid: true,
             appendToast: false,
         })
     },
 };
+
+// Get the bootstrap variant for an announcement severity.
+export function severityVariant(severity) {
+    switch(severity) {
+        case 'critical':
+            return 'danger';
+        case 'warning':
+            return 'warning';
+        default:
+            return 'info';
+    }
+}
@@ -97,6 +97,42 @@ export default {
         return this.session != null && this.session.role_admin;
     },
 
+    /**
+     * Check whether the user has the referee role, admins are referees as well.
+     */
+    hasRoleReferee() {
+        return this.session != null && this.session.role_referee;
+    },
+
+    /**
+     * Check whether the user may show the code of the given outpost.
+     *
+     * If no outpost ID is given, check whether the user may show any outpost code.
+     */
+    hasRoleOutpost(id) {
+        if(this.session == null)
+            return false;
+        if(this.session.role_referee)
+            return true;
+        if(id === undefined)
+            return this.session.outposts.length > 0;
+        return this.session.outposts.includes(id);
+    },
+
+    /**
+     * Get the outpost IDs the user may show the code of through an outpost role.
+     */
+    outposts() {
+        return this.session != null ? this.session.outposts : [];
+    },
+
+    /**
+     * Get the game ID of the current session, null if unknown.
+     */
+    game() {
+        return this.session != null ? this.session.game : null;
+    },
+
     /**
      * Set session.
      */
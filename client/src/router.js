@@ -9,6 +9,7 @@ import Leaderboard from "./views/Leaderboard.vue";
 import Admin from "./views/Admin.vue";
 import Outpost from "./views/Outpost.vue";
 import About from "./views/About.vue";
+import Spectate from "./views/Spectate.vue";
 
 Vue.use(Router);
 
@@ -58,6 +59,11 @@ export default new Router({
       name: "outpost",
       component: Outpost,
     },
+    {
+      path: "/spectate",
+      name: "spectate",
+      component: Spectate,
+    },
     {
       path: "/about",
       name: "about",
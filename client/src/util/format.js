@@ -10,4 +10,12 @@ export default {
       return parseFloat(value * 100).toPrecision(2) + '%';
     return parseFloat(value * 100).toPrecision(1) + '%';
   },
+
+  formatDuration(seconds) {
+    seconds = Math.max(0, seconds);
+    let hours = Math.floor(seconds / 3600);
+    let minutes = Math.floor(seconds / 60) % 60;
+    let pad = (value) => String(value).padStart(2, '0');
+    return (hours > 0 ? hours + ':' + pad(minutes) : minutes) + ':' + pad(seconds % 60);
+  },
 };
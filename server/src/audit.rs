@@ -0,0 +1,178 @@
+//! Audit log of admin actions.
+//!
+//! Every admin action is recorded with who did it, when, to whom, and the values before and
+//! after. The log is appended to a JSON lines file, so it survives restarts and game resets.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::util::csv_escape;
+
+/// An audited admin action.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Time of the action, in seconds since the UNIX epoch.
+    pub time: u64,
+
+    /// Game tick of the action.
+    pub tick: u64,
+
+    /// Admin user that did the action.
+    pub admin: u32,
+
+    /// Action name, such as `reward_user`.
+    pub action: String,
+
+    /// User the action was for, if any.
+    #[serde(default)]
+    pub target: Option<u32>,
+
+    /// Value before the action, if any.
+    #[serde(default)]
+    pub before: Value,
+
+    /// Value after the action, or the action details.
+    #[serde(default)]
+    pub after: Value,
+}
+
+/// Audit log query.
+#[derive(Deserialize, Debug, Default)]
+pub struct AuditQuery {
+    /// Optional: only entries for this target user.
+    #[serde(default)]
+    pub target: Option<u32>,
+
+    /// Optional: only entries by this admin.
+    #[serde(default)]
+    pub admin: Option<u32>,
+
+    /// Optional: only entries after this time, in seconds since the UNIX epoch.
+    #[serde(default)]
+    pub since: Option<u64>,
+
+    /// Optional: only the latest number of matching entries.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    /// Check whether the given entry matches.
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.target.map(|t| entry.target == Some(t)).unwrap_or(true)
+            && self.admin.map(|a| entry.admin == a).unwrap_or(true)
+            && self.since.map(|s| entry.time > s).unwrap_or(true)
+    }
+}
+
+/// Persisted audit log.
+#[derive(Default)]
+pub struct AuditLog {
+    /// All entries, oldest first.
+    entries: RwLock<Vec<AuditEntry>>,
+
+    /// File to append entries to.
+    file: Mutex<Option<File>>,
+}
+
+impl AuditLog {
+    /// Open audit log file, loading existing entries and creating it if it doesn't exist.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let entries = if path.is_file() { read(path)? } else { vec![] };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            entries: RwLock::new(entries),
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    /// Record an entry.
+    ///
+    /// Failing to write is logged but otherwise ignored, the entry is still kept in memory.
+    pub fn record(&self, entry: AuditEntry) {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let result = serde_json::to_vec(&entry)
+                .map_err(io::Error::from)
+                .and_then(|mut data| {
+                    data.push(b'\n');
+                    file.write_all(&data)
+                });
+            if let Err(err) = result {
+                error!("Failed to write audit log entry: {}", err);
+            }
+        }
+        entries.push(entry);
+    }
+
+    /// Query entries, oldest first.
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        let entries = self.entries.read().unwrap();
+        let mut entries: Vec<_> = entries.iter().filter(|e| query.matches(e)).collect();
+        if let Some(limit) = query.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        entries.into_iter().cloned().collect()
+    }
+}
+
+/// Read all entries from an audit log file.
+///
+/// A partial last line, such as after a crash while writing, is skipped.
+pub fn read(path: &Path) -> io::Result<Vec<AuditEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines().enumerate().peekable();
+    let mut entries = vec![];
+    while let Some((i, line)) = lines.next() {
+        match serde_json::from_str(&line?) {
+            Ok(entry) => entries.push(entry),
+            Err(err) if lines.peek().is_none() => {
+                warn!("Skipping partial last entry in audit log: {}", err);
+            }
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid audit entry on line {}: {}", i + 1, err),
+                ));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Format entries as CSV, values are formatted as JSON.
+pub fn to_csv(entries: &[AuditEntry]) -> String {
+    let mut csv = String::from("time,tick,admin,action,target,before,after\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            entry.time,
+            entry.tick,
+            entry.admin,
+            csv_escape(&entry.action),
+            entry.target.map(|t| t.to_string()).unwrap_or_default(),
+            csv_escape(&csv_value(&entry.before)),
+            csv_escape(&csv_value(&entry.after)),
+        ));
+    }
+    csv
+}
+
+/// Format a value for CSV, empty if null.
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Audit log file path for the given game save file path.
+pub fn path_for(game_path: &Path) -> PathBuf {
+    game_path.with_file_name(crate::AUDIT_LOG_FILE)
+}
@@ -1,45 +1,98 @@
 use std::fs;
+use std::io;
 use std::iter;
 use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc, RwLock,
 };
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Argon2, PasswordHasher, PasswordVerifier};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 use tokio::sync::mpsc;
+use tokio::time;
 use warp::filters::ws::{Message, WebSocket};
 
+use crate::state::SharedServer;
+
 /// Session token length.
 const TOKEN_LENGTH: usize = 64;
 
+/// Public session ID length.
+const SESSION_ID_LENGTH: usize = 16;
+
+/// Prefix of configured passwords that are hashed.
+const PASSWORD_HASH_PREFIX: &str = "$argon2";
+
 /// Unique client ID provider.
 static CLIENT_IDS: AtomicUsize = AtomicUsize::new(1);
 
+/// Session pruning loop.
+///
+/// Periodically prunes expired sessions, and saves sessions to persist their last seen time.
+pub(crate) async fn prune_loop(server: SharedServer) {
+    let mut interval = time::interval(Duration::from_secs(crate::SESSION_PRUNE_INTERVAL_SEC));
+
+    loop {
+        // Wait for tick
+        interval.tick().await;
+
+        server.prune_sessions();
+
+        // Save sessions, keep backups
+        if let Err(err) = server.sessions.save() {
+            error!("Failed to save sessions: {}", err);
+            continue;
+        }
+        let path = &server.sessions.path;
+        if let Err(err) = crate::save::backup(path, crate::SESSIONS_SAVE_BACKUP_COUNT) {
+            error!("Failed to back up sessions: {}", err);
+        }
+    }
+}
+
 /// A basic session manager.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SessionManager {
     sessions: RwLock<Vec<Session>>,
+
+    /// Sessions file path.
+    #[serde(skip)]
+    path: PathBuf,
 }
 
 impl SessionManager {
-    /// Construct a new session manager.
-    pub fn new() -> Self {
+    /// Construct a new session manager, saving to the given path.
+    pub fn new(path: PathBuf) -> Self {
         Self {
             sessions: RwLock::new(vec![]),
+            path,
         }
     }
 
-    /// Add session for a given user.
+    /// Add session for a given user in a game.
+    ///
+    /// The session expires after `max_age` if given. The `device` describes the device the
+    /// session is used on, such as its user agent.
     ///
     /// Returns session with corresponding token.
-    pub fn add(&self, user_id: u32) -> Session {
-        let session = Session::new_random_token(user_id);
+    pub fn add(
+        &self,
+        game: &str,
+        user_id: u32,
+        max_age: Option<Duration>,
+        device: Option<String>,
+    ) -> Session {
+        let mut session = Session::new_random_token(game, user_id);
+        session.expires = max_age.map(|age| session.created + age.as_secs());
+        session.device = device;
         self.sessions.write().unwrap().push(session.clone());
 
-        // TODO: properly save, handle errors
-        self.save().expect("failed to save");
+        self.save_or_log();
 
         session
     }
@@ -56,25 +109,78 @@ impl SessionManager {
             None => return false,
         }
 
-        // TODO: properly save, handle errors
-        self.save().expect("failed to save");
+        self.save_or_log();
 
         true
     }
 
-    /// Get a session.
-    pub fn get(&self, token: &str) -> Option<Session> {
+    /// Remove session with the given public session ID.
+    ///
+    /// Returns the removed session.
+    pub fn remove_by_id(&self, id: &str) -> Option<Session> {
+        let mut sessions = self.sessions.write().unwrap();
+        let i = sessions.iter().position(|session| session.id == id)?;
+        let session = sessions.remove(i);
+        drop(sessions);
+
+        self.save_or_log();
+
+        Some(session)
+    }
+
+    /// Remove all sessions that don't match the predicate.
+    ///
+    /// Returns the removed sessions.
+    pub fn retain<F>(&self, mut f: F) -> Vec<Session>
+    where
+        F: FnMut(&Session) -> bool,
+    {
+        let mut sessions = self.sessions.write().unwrap();
+        let (keep, removed) = sessions.drain(..).partition(|session| f(session));
+        *sessions = keep;
+        drop(sessions);
+
+        if !removed.is_empty() {
+            self.save_or_log();
+        }
+
+        removed
+    }
+
+    /// List sessions in a game, optionally only for the given user.
+    pub fn list(&self, game: &str, user_id: Option<u32>) -> Vec<Session> {
         self.sessions
             .read()
             .unwrap()
             .iter()
-            .find(|session| session.is_token_and_valid(token))
+            .filter(|session| session.game == game)
+            .filter(|session| user_id.map(|id| session.user_id == id).unwrap_or(true))
             .cloned()
+            .collect()
     }
 
-    /// Get a session and explicitly check it is valid.
-    pub fn get_valid(&self, token: &str) -> Option<Session> {
-        self.get(token)
+    /// Mark the session with the given token as seen now.
+    pub fn touch(&self, token: &str) {
+        let now = now();
+        if let Some(session) = self
+            .sessions
+            .write()
+            .unwrap()
+            .iter_mut()
+            .find(|session| session.is_token(token))
+        {
+            session.last_seen = now;
+        }
+    }
+
+    /// Get a session, only if it is valid.
+    pub fn get(&self, token: &str) -> Option<Session> {
+        self.sessions
+            .read()
+            .unwrap()
+            .iter()
+            .find(|session| session.is_token_and_valid(token))
+            .cloned()
     }
 
     /// Check whether the given token is valid.
@@ -87,58 +193,42 @@ impl SessionManager {
     }
 
     /// Load sessions from file.
-    pub fn load() -> Result<Self, ()> {
-        // Load default if file doesn't exist
-        let path = PathBuf::from(crate::SESSIONS_SAVE_PATH);
-        if !path.is_file() {
-            info!("No sessions file, starting fresh");
-            return Ok(Self::new());
-        }
-
-        // Load data from file
+    pub fn load(path: PathBuf) -> Result<Self, ()> {
+        // Load from file, falling back to backups if it is corrupt
         info!("Loading sessions from file");
-        trace!("Reading sessions file...");
-        let data = fs::read(path).expect("failed to read sessions file");
-
-        // Deserialize
-        trace!("Deserializing sessions data...");
-        match serde_json::from_slice(data.as_slice()) {
-            Ok(state) => Ok(state),
-            Err(err) => {
-                error!(
-                    "Failed to load sessions from file, couldn't deserialize: {}",
-                    err
-                );
-                Err(())
+        let sessions = crate::save::load(&path, |data| {
+            trace!("Deserializing sessions data...");
+            serde_json::from_slice::<Self>(data)
+                .map_err(|err| format!("couldn't deserialize sessions: {}", err))
+        })?;
+
+        // Load default if file doesn't exist
+        match sessions {
+            Some(sessions) => Ok(Self { path, ..sessions }),
+            None => {
+                info!("No sessions file, starting fresh");
+                Ok(Self::new(path))
             }
         }
     }
 
     /// Save sessions to file.
-    pub fn save(&self) -> Result<(), ()> {
+    pub fn save(&self) -> io::Result<()> {
         info!("Saving sessions to file");
 
         // Serialize sessions
         trace!("Serializing sessions...");
-        let data = match serde_json::to_vec(self) {
-            Ok(data) => data,
-            Err(err) => {
-                error!(
-                    "Failed to save sessions to file, couldn't serialize: {}",
-                    err
-                );
-                return Err(());
-            }
-        };
+        let data = serde_json::to_vec(self)?;
 
         // Write to file
         trace!("Writing sessions to file...");
-        match fs::write(crate::SESSIONS_SAVE_PATH, data.as_slice()) {
-            Ok(result) => Ok(result),
-            Err(err) => {
-                error!("Failed to save sessions to file: {}", err);
-                Err(())
-            }
+        crate::save::write_atomic(&self.path, data.as_slice())
+    }
+
+    /// Save sessions to file, logging instead of returning any error.
+    fn save_or_log(&self) {
+        if let Err(err) = self.save() {
+            error!("Failed to save sessions: {}", err);
         }
     }
 }
@@ -147,20 +237,51 @@ impl SessionManager {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 // TODO: add admin property
 pub struct Session {
+    /// Public session ID, used to refer to a session without exposing its token.
+    #[serde(default = "generate_session_id")]
+    pub id: String,
+
+    // Game this session is for.
+    #[serde(default = "default_game")]
+    pub game: String,
+
     // User this session is for.
     // TODO: make option
     pub user_id: u32,
 
     // Session token.
     token: String,
+
+    /// Creation time, in seconds since the UNIX epoch.
+    #[serde(default = "now")]
+    pub created: u64,
+
+    /// Last time the session was used, in seconds since the UNIX epoch.
+    #[serde(default = "now")]
+    pub last_seen: u64,
+
+    /// Optional: expiry time, in seconds since the UNIX epoch.
+    #[serde(default)]
+    pub expires: Option<u64>,
+
+    /// Optional: device the session is used on, such as its user agent.
+    #[serde(default)]
+    pub device: Option<String>,
 }
 
 impl Session {
     /// Construct a new session with a random token.
-    fn new_random_token(user_id: u32) -> Self {
+    fn new_random_token(game: &str, user_id: u32) -> Self {
+        let now = now();
         Self {
+            id: generate_session_id(),
+            game: game.into(),
             user_id,
             token: generate_token(),
+            created: now,
+            last_seen: now,
+            expires: None,
+            device: None,
         }
     }
 
@@ -171,7 +292,12 @@ impl Session {
 
     /// Check whether the given token matches this session and this session is valid.
     pub fn is_token_and_valid(&self, token: &str) -> bool {
-        self.is_token(token)
+        self.is_token(token) && !self.is_expired(now())
+    }
+
+    /// Check whether this session is expired at the given time.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
     }
 
     /// Get session token.
@@ -185,6 +311,9 @@ impl Session {
 /// Tracks active client websocket connections.
 pub struct ClientManager {
     pub clients: RwLock<Vec<Client>>,
+
+    /// Spectator connections, these only receive spectator messages.
+    pub spectators: RwLock<Vec<Spectator>>,
 }
 
 impl ClientManager {
@@ -192,9 +321,28 @@ impl ClientManager {
     pub fn new() -> Self {
         Self {
             clients: RwLock::new(vec![]),
+            spectators: RwLock::new(vec![]),
         }
     }
 
+    /// Register a spectator.
+    pub fn register_spectator(&self, spectator: Spectator) {
+        self.spectators.write().unwrap().push(spectator);
+    }
+
+    /// Unregister a spectator.
+    pub fn unregister_spectator(&self, client_id: usize) {
+        self.spectators
+            .write()
+            .unwrap()
+            .retain(|s| s.client_id != client_id);
+    }
+
+    /// Check whether any spectator is connected.
+    pub fn has_spectators(&self) -> bool {
+        !self.spectators.read().unwrap().is_empty()
+    }
+
     /// Register a client.
     pub fn register(&self, client: Client) {
         self.clients.write().unwrap().push(client);
@@ -222,6 +370,46 @@ impl ClientManager {
             .collect()
     }
 
+    /// Check whether the given client is registered.
+    pub fn is_registered(&self, client_id: usize) -> bool {
+        self.clients
+            .read()
+            .unwrap()
+            .iter()
+            .any(|c| c.client_id == client_id)
+    }
+
+    /// Forcibly close and unregister all clients using the given session.
+    ///
+    /// Returns the number of closed clients.
+    pub fn close_session(&self, session_id: &str) -> usize {
+        let mut clients = self.clients.write().unwrap();
+        let before = clients.len();
+        clients.retain(|client| {
+            if client.session_id != session_id {
+                return true;
+            }
+            if let Err(err) = client.tx.send(Message::close()) {
+                debug!(
+                    "Failed to send close to client {}: {}",
+                    client.client_id, err
+                );
+            }
+            false
+        });
+        before - clients.len()
+    }
+
+    /// Count the clients using the given session.
+    pub fn session_client_count(&self, session_id: &str) -> usize {
+        self.clients
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|c| c.session_id == session_id)
+            .count()
+    }
+
     /// Find the user ID for a given client.
     pub fn client_user_id(&self, client_id: usize) -> Option<u32> {
         let clients = self.clients.read().unwrap();
@@ -237,6 +425,9 @@ pub struct Client {
     /// Authenticated user ID.
     pub user_id: u32,
 
+    /// Public ID of the session the client authenticated with.
+    pub session_id: String,
+
     /// Message send queue.
     // TODO: make this private, send through JSON serialize function instead
     pub tx: mpsc::UnboundedSender<Message>,
@@ -244,15 +435,25 @@ pub struct Client {
 
 impl Client {
     /// Construct a new client.
-    pub fn new(client_id: usize, user_id: u32, tx: mpsc::UnboundedSender<Message>) -> Self {
+    pub fn new(client_id: usize, session: &Session, tx: mpsc::UnboundedSender<Message>) -> Self {
         Self {
             client_id,
-            user_id,
+            user_id: session.user_id,
+            session_id: session.id.clone(),
             tx,
         }
     }
 }
 
+/// An unauthenticated spectator connection.
+pub struct Spectator {
+    /// Unique websocket client ID.
+    pub client_id: usize,
+
+    /// Message send queue.
+    pub tx: mpsc::UnboundedSender<Message>,
+}
+
 /// Session data.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SessionToken {
@@ -269,15 +470,78 @@ fn valid_token_format(token: &str) -> bool {
 
 /// Generate a secure random token.
 fn generate_token() -> String {
+    random_alphanumeric(TOKEN_LENGTH)
+}
+
+/// Generate a random public session ID.
+fn generate_session_id() -> String {
+    random_alphanumeric(SESSION_ID_LENGTH)
+}
+
+/// Generate a secure random alphanumeric string of the given length.
+fn random_alphanumeric(len: usize) -> String {
     let mut rng = thread_rng();
     iter::repeat(())
         .map(|()| rng.sample(Alphanumeric))
         .map(char::from)
-        .take(TOKEN_LENGTH)
+        .take(len)
         .collect()
 }
 
+/// Current time, in seconds since the UNIX epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Game for sessions created before hosting multiple games.
+fn default_game() -> String {
+    crate::config::registry::DEFAULT_GAME_ID.into()
+}
+
 /// Generate an unique client ID.
 pub fn generate_client_id() -> usize {
     CLIENT_IDS.fetch_add(1, Ordering::Relaxed)
 }
+
+/// Hash a password, producing a salted argon2 hash in PHC string format.
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("failed to hash password")
+        .to_string()
+}
+
+/// Check whether a configured password is a hash in PHC string format, rather than plaintext.
+pub fn is_password_hash(stored: &str) -> bool {
+    stored.starts_with(PASSWORD_HASH_PREFIX)
+}
+
+/// Check whether a configured password hash can be parsed, and has a salt and hash.
+pub fn is_valid_password_hash(stored: &str) -> bool {
+    PasswordHash::new(stored)
+        .map(|hash| hash.salt.is_some() && hash.hash.is_some())
+        .unwrap_or(false)
+}
+
+/// Verify a password against a configured password, which is a hash or plaintext.
+///
+/// Comparison is done in constant time.
+pub fn verify_password(stored: &str, password: &str) -> bool {
+    if !is_password_hash(stored) {
+        return stored.as_bytes().ct_eq(password.as_bytes()).into();
+    }
+
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(err) => {
+            error!("Failed to parse configured password hash: {}", err);
+            false
+        }
+    }
+}
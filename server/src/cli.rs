@@ -0,0 +1,394 @@
+//! Command line interface.
+
+use std::fs;
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::Ordering;
+
+use clap::{Parser, Subcommand};
+
+use crate::audit;
+use crate::config::registry::{self, RegistryGame};
+use crate::config::{self, Config};
+use crate::game::Game;
+use crate::game::{code, journal};
+use crate::sim;
+use crate::util::csv_escape;
+
+/// Merge Mania server.
+#[derive(Parser, Debug)]
+#[command(name = "mms", version, about)]
+pub struct Cli {
+    /// Game configuration file.
+    #[arg(
+        short,
+        long,
+        env = "MMS_CONFIG",
+        default_value = crate::CONFIG_PATH,
+        global = true
+    )]
+    pub config: PathBuf,
+
+    /// Game registry file, to host multiple games. Overrides --config.
+    #[arg(short, long, env = "MMS_GAMES", global = true)]
+    pub games: Option<PathBuf>,
+
+    /// Game to run a command for, when hosting multiple games.
+    #[arg(long, global = true)]
+    pub game: Option<String>,
+
+    /// Directory to store save files in.
+    #[arg(short, long, env = "MMS_SAVE_DIR", default_value = ".", global = true)]
+    pub save_dir: PathBuf,
+
+    /// Address to bind the web server to.
+    #[arg(long, env = "MMS_HOST", default_value = "0.0.0.0", global = true)]
+    pub host: IpAddr,
+
+    /// Port to bind the web server to.
+    #[arg(short, long, env = "MMS_PORT", default_value_t = 8000, global = true)]
+    pub port: u16,
+
+    /// Command to run, defaults to serve.
+    #[command(subcommand)]
+    pub cmd: Option<Cmd>,
+}
+
+impl Cli {
+    /// Web server address to bind to.
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    /// List of games to host.
+    ///
+    /// Loads the game registry if configured, exit with a report on failure.
+    pub fn registry_games(&self) -> Vec<RegistryGame> {
+        let path = match &self.games {
+            Some(path) => path,
+            None => return vec![RegistryGame::single(self.config.clone())],
+        };
+        match registry::load(path) {
+            Ok(registry) => registry.games,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Select the game to run a command for, exit on failure.
+    pub fn select_game(&self) -> RegistryGame {
+        let mut games = self.registry_games();
+        let index = match &self.game {
+            Some(id) => games.iter().position(|g| &g.id == id),
+            None if games.len() == 1 => Some(0),
+            None => {
+                eprintln!("error: hosting multiple games, select one with --game");
+                process::exit(1);
+            }
+        };
+        match index {
+            Some(index) => games.swap_remove(index),
+            None => {
+                eprintln!("error: unknown game, not in registry");
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Game save file path.
+    ///
+    /// When hosting from a registry, each game is saved in its own subdirectory.
+    pub fn game_save_path(&self, game: &RegistryGame) -> PathBuf {
+        if self.games.is_some() {
+            self.save_dir.join(&game.id).join(crate::GAME_SAVE_FILE)
+        } else {
+            self.save_dir.join(crate::GAME_SAVE_FILE)
+        }
+    }
+
+    /// Sessions save file path.
+    pub fn sessions_save_path(&self) -> PathBuf {
+        self.save_dir.join(crate::SESSIONS_SAVE_FILE)
+    }
+
+    /// Load game configuration, exit with a report on failure.
+    pub fn load_config(&self, game: &RegistryGame) -> Config {
+        match config::load(&game.config) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Load saved game state, exit on failure.
+    fn load_game(&self, game: &RegistryGame, config: &Config) -> Game {
+        match Game::load(config, &self.game_save_path(game)) {
+            Ok(game) => game,
+            Err(()) => {
+                eprintln!("error: failed to load game state, see log for details");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Cmd {
+    /// Start the game server.
+    Serve,
+
+    /// Validate the configuration of all games.
+    CheckConfig,
+
+    /// Remove the saved state of a game, a new game is started on next start.
+    ResetSave {
+        /// Also remove all sessions, logging out every device.
+        #[arg(long)]
+        sessions: bool,
+    },
+
+    /// Print the saved game state as JSON.
+    DumpState,
+
+    /// Export user stats from the saved game state as CSV.
+    ExportStats,
+
+    /// Print the admin audit log as CSV.
+    ExportAudit,
+
+    /// Print a printable HTML sheet with the static QR-code of each outpost.
+    OutpostSheet,
+
+    /// Rebuild game state by replaying the game event journal.
+    Replay {
+        /// Stop replaying after this game tick.
+        #[arg(long)]
+        until_tick: Option<u64>,
+
+        /// Write the rebuilt state to this save file, instead of printing it as JSON.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Simulate a game played by bots, to validate the item economy configuration.
+    Simulate {
+        #[command(flatten)]
+        options: sim::Options,
+
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Hash a password, to use as user password in the configuration.
+    HashPassword {
+        /// Password to hash, read from stdin if not given.
+        password: Option<String>,
+    },
+}
+
+/// Validate the configuration of all games.
+pub fn check_config(cli: &Cli) {
+    for game in cli.registry_games() {
+        let config = cli.load_config(&game);
+        println!(
+            "Config {} of game '{}' is valid ({} users, {} items)",
+            game.config.display(),
+            game.id,
+            config.users.len(),
+            config.items.len()
+        );
+        for user in config.users.iter().filter(|u| u.has_plaintext_password()) {
+            println!("  warning: user {} has a plaintext password", user.id);
+        }
+    }
+}
+
+/// Remove saved game state.
+pub fn reset_save(cli: &Cli, sessions: bool) {
+    let game_path = cli.game_save_path(&cli.select_game());
+    let mut paths = vec![
+        journal::path_for(&game_path),
+        code::path_for(&game_path),
+        game_path,
+    ];
+    if sessions {
+        paths.push(cli.sessions_save_path());
+    }
+
+    for path in paths {
+        if let Err(err) = remove_file(&path) {
+            eprintln!("error: failed to remove {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the saved game state as JSON.
+pub fn dump_state(cli: &Cli) {
+    let game = cli.select_game();
+    let config = cli.load_config(&game);
+    let game = cli.load_game(&game, &config);
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    serde_json::to_writer_pretty(&mut stdout, &game).expect("failed to serialize game state");
+    let _ = writeln!(stdout);
+}
+
+/// Rebuild game state from the game event journal.
+pub fn replay(cli: &Cli, until_tick: Option<u64>, output: Option<&Path>) {
+    let game = cli.select_game();
+    let config = cli.load_config(&game);
+    let path = journal::path_for(&cli.game_save_path(&game));
+    let events = match journal::read(&path) {
+        Ok(events) => events,
+        Err(err) => {
+            eprintln!("error: failed to read journal {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    };
+
+    let game = Game::replay(&config, &events, until_tick);
+    eprintln!("Replayed journal up to tick {}", game.tick());
+
+    match output {
+        Some(path) => {
+            if game.save(path).is_err() {
+                eprintln!("error: failed to save game state, see log for details");
+                process::exit(1);
+            }
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            serde_json::to_writer_pretty(&mut stdout, &game)
+                .expect("failed to serialize game state");
+            let _ = writeln!(stdout);
+        }
+    }
+}
+
+/// Export user stats from the saved game state as CSV.
+pub fn export_stats(cli: &Cli) {
+    let game = cli.select_game();
+    let config = cli.load_config(&game);
+    let game = cli.load_game(&game, &config);
+
+    println!(
+        "id,name,money,energy,discovered,merge_count,buy_count,sell_count,swap_count,\
+        code_count,drop_count,trade_count,mission_count,money_spent,money_earned,energy_spent,\
+        energy_earned"
+    );
+
+    let users = game.users.read().unwrap();
+    let mut ids: Vec<_> = users.keys().cloned().collect();
+    ids.sort_unstable();
+    for id in ids {
+        let user = users[&id].read().unwrap();
+        let name = config.user(id).map(|u| u.name.as_str()).unwrap_or("");
+        let stats = &user.stats;
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            id,
+            csv_escape(name),
+            user.inventory.money,
+            user.inventory.energy,
+            user.inventory.discovered.len(),
+            stats.merge_count.load(Ordering::Relaxed),
+            stats.buy_count.load(Ordering::Relaxed),
+            stats.sell_count.load(Ordering::Relaxed),
+            stats.swap_count.load(Ordering::Relaxed),
+            stats.code_count.load(Ordering::Relaxed),
+            stats.drop_count.load(Ordering::Relaxed),
+            stats.trade_count.load(Ordering::Relaxed),
+            stats.mission_count.load(Ordering::Relaxed),
+            stats.money_spent.load(Ordering::Relaxed),
+            stats.money_earned.load(Ordering::Relaxed),
+            stats.energy_spent.load(Ordering::Relaxed),
+            stats.energy_earned.load(Ordering::Relaxed),
+        );
+    }
+}
+
+/// Print the admin audit log as CSV.
+pub fn export_audit(cli: &Cli) {
+    let game = cli.select_game();
+    let path = audit::path_for(&cli.game_save_path(&game));
+    if !path.is_file() {
+        eprintln!("error: game has no audit log");
+        process::exit(1);
+    }
+    match audit::read(&path) {
+        Ok(entries) => print!("{}", audit::to_csv(&entries)),
+        Err(err) => {
+            eprintln!("error: failed to read audit log: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print a printable HTML sheet with the static QR-code of each outpost.
+pub fn outpost_sheet(cli: &Cli) {
+    let game = cli.select_game();
+    let config = cli.load_config(&game);
+    match crate::qr::sheet(&config) {
+        Some(sheet) => print!("{}", sheet),
+        None => {
+            eprintln!("error: no outposts with static_code enabled");
+            process::exit(1);
+        }
+    }
+}
+
+/// Simulate a game played by bots, and print a report.
+pub fn simulate(cli: &Cli, options: &sim::Options, json: bool) {
+    let game = cli.select_game();
+    let config = cli.load_config(&game);
+    let report = sim::run(&config, options);
+
+    if json {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer_pretty(&mut stdout, &report).expect("failed to serialize report");
+        let _ = writeln!(stdout);
+    } else {
+        report.print();
+    }
+}
+
+/// Hash a password, to use as user password in the configuration.
+pub fn hash_password(password: Option<&str>) {
+    let password = match password {
+        Some(password) => password.to_string(),
+        None => {
+            let mut line = String::new();
+            if let Err(err) = io::stdin().read_line(&mut line) {
+                eprintln!("error: failed to read password from stdin: {}", err);
+                process::exit(1);
+            }
+            line.trim_end_matches(&['\r', '\n'][..]).to_string()
+        }
+    };
+
+    println!("{}", crate::auth::hash_password(&password));
+}
+
+/// Remove a file, succeeds if it doesn't exist.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            println!("Removed {}", path.display());
+            Ok(())
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
@@ -1,6 +1,9 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::types::ItemRef;
+use crate::game::admin::InventoryEdit;
+use crate::game::announce::{AnnouncementTarget, Severity};
+use crate::game::sanction::Sanction;
+use crate::types::{Amount, ItemRef};
 
 /// Client action: swap two cells.
 #[derive(Deserialize, Debug)]
@@ -35,3 +38,66 @@ pub struct ClientActionRewardUser {
     pub outpost_id: u32,
     pub user_id: u32,
 }
+
+/// Client action: enable or disable an outpost, as admin.
+#[derive(Deserialize, Debug)]
+pub struct ClientActionSetOutpostEnabled {
+    pub outpost_id: u32,
+    pub enabled: bool,
+}
+
+/// Client action: admin edit of a user inventory.
+#[derive(Deserialize, Debug)]
+pub struct ClientActionEditInventory {
+    pub user_id: u32,
+    pub edit: InventoryEdit,
+}
+
+/// Client action: make an announcement, as admin.
+#[derive(Deserialize, Debug)]
+pub struct ClientActionAnnounce {
+    /// Users to show the announcement to.
+    pub target: AnnouncementTarget,
+
+    /// Severity level.
+    #[serde(default)]
+    pub severity: Severity,
+
+    /// Whether to show the announcement until dismissed.
+    #[serde(default)]
+    pub persistent: bool,
+
+    /// Announcement text.
+    pub text: String,
+}
+
+/// Client action: apply a sanction or bonus to a user, as admin.
+#[derive(Deserialize, Debug)]
+pub struct ClientActionSanction {
+    pub user_id: u32,
+    pub sanction: Sanction,
+    pub reason: String,
+}
+
+/// Client action: offer a trade to another user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientActionTradeOffer {
+    /// User to offer the trade to.
+    pub to: u32,
+
+    /// Inventory cells to offer.
+    #[serde(default)]
+    pub cells: Vec<u8>,
+
+    /// Money to offer.
+    #[serde(default)]
+    pub money: u64,
+
+    /// Energy to offer.
+    #[serde(default)]
+    pub energy: u64,
+
+    /// Amounts to request in return.
+    #[serde(default)]
+    pub request: Vec<Amount>,
+}
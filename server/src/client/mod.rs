@@ -10,7 +10,11 @@ use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::audit::{AuditEntry, AuditQuery};
 use crate::config::ConfigItem;
+use crate::game::announce::Announcement;
+use crate::game::history::HistorySnapshot;
+use crate::game::sanction::SanctionRecord;
 use crate::types::ItemRef;
 pub use action::*;
 pub use types::*;
@@ -53,6 +57,12 @@ pub enum MsgSendKind {
     /// Game state, whether it's running.
     GameState(bool),
 
+    /// Timed round state, with time left to count down.
+    Round(ClientRound),
+
+    /// Final scoreboard, frozen when the round finished.
+    FinalScoreboard(Vec<ClientLeaderboardUser>),
+
     /// Game item configuration.
     ConfigItems(HashMap<ItemRef, ConfigItem>),
 
@@ -80,8 +90,50 @@ pub enum MsgSendKind {
     /// Whether the last scanned QR code was valid.
     CodeResult(bool),
 
-    /// Send token to outpost to render.
-    OutpostToken(String),
+    /// Send token to outpost to render, and seconds after which to request a new one.
+    OutpostToken { token: String, interval_secs: u64 },
+
+    /// List of configured outposts, for admins.
+    Outposts(Vec<ClientOutpost>),
+
+    /// Owners of captured outposts.
+    OutpostOwners(Vec<ClientOutpostOwner>),
+
+    /// List of pending trades the user is involved in.
+    Trades(Vec<ClientTrade>),
+
+    /// A new trade offer the user is involved in.
+    Trade(ClientTrade),
+
+    /// A pending trade was closed, either accepted or declined.
+    TradeClosed { id: u32, accepted: bool },
+
+    /// List of currently available missions.
+    Missions(Vec<ClientMission>),
+
+    /// List of active sessions in the game.
+    Sessions(Vec<ClientUserSession>),
+
+    /// Leaderboard history snapshots.
+    History(Vec<HistorySnapshot>),
+
+    /// Audit log entries, oldest first.
+    Audit(Vec<AuditEntry>),
+
+    /// A sanction or bonus applied to the current client user.
+    Sanction(SanctionRecord),
+
+    /// A new announcement.
+    Announcement(Announcement),
+
+    /// All announcements shown to the current client user, oldest first.
+    Announcements(Vec<Announcement>),
+
+    /// Inventory state of another user, for admins.
+    UserInventory {
+        user_id: u32,
+        inventory: ClientInventory,
+    },
 }
 
 /// Message kinds to receive from a client.
@@ -130,4 +182,77 @@ pub enum MsgRecvKind {
 
     /// Reward the given user for the given outpost.
     ActionRewardUser(ClientActionRewardUser),
+
+    /// Request list of configured outposts.
+    GetOutposts,
+
+    /// Action: enable or disable an outpost.
+    ActionSetOutpostEnabled(ClientActionSetOutpostEnabled),
+
+    /// Request owners of captured outposts.
+    GetOutpostOwners,
+
+    /// Request list of pending trades.
+    GetTrades,
+
+    /// Action: offer a trade to another user.
+    ActionTradeOffer(ClientActionTradeOffer),
+
+    /// Action: accept a received trade offer.
+    ActionTradeAccept(u32),
+
+    /// Action: decline a received trade offer, or withdraw a sent one.
+    ActionTradeDecline(u32),
+
+    /// Request list of available missions.
+    GetMissions,
+
+    /// Action: submit a mission, delivering its required amounts.
+    ActionSubmitMission(u32),
+
+    /// Request list of active sessions, optionally for a single user.
+    GetSessions(Option<u32>),
+
+    /// Action: revoke a session by its ID, closing its clients.
+    ActionRevokeSession(String),
+
+    /// Action: revoke all sessions of a user, closing their clients.
+    ActionRevokeUserSessions(u32),
+
+    /// Request leaderboard history, optionally only snapshots after the given tick.
+    GetHistory(Option<u64>),
+
+    /// Request inventory state of the given user, for admins.
+    GetUserInventory(u32),
+
+    /// Action: edit the inventory of a user, for admins.
+    ActionEditInventory(ClientActionEditInventory),
+
+    /// Action: make an announcement, for admins.
+    ActionAnnounce(ClientActionAnnounce),
+
+    /// Action: apply a sanction or bonus to a user, for admins.
+    ActionSanction(ClientActionSanction),
+
+    /// Request audit log entries matching the query, for admins.
+    GetAudit(AuditQuery),
+}
+
+impl MsgRecvKind {
+    /// Whether this is a game action by the user itself, which frozen users can't do.
+    pub fn is_user_action(&self) -> bool {
+        use MsgRecvKind::*;
+        matches!(
+            self,
+            ActionSwap(_)
+                | ActionMerge(_)
+                | ActionBuy(_)
+                | ActionSell(_)
+                | ActionScanCode(_)
+                | MockScanCode
+                | ActionTradeOffer(_)
+                | ActionTradeAccept(_)
+                | ActionSubmitMission(_)
+        )
+    }
 }
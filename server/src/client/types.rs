@@ -2,10 +2,14 @@ use std::collections::HashSet;
 use std::sync::atomic::Ordering;
 
 use rand::prelude::*;
-use serde::Serialize;
-
-use crate::auth::Session;
-use crate::config::Config;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{ClientManager, Session};
+use crate::config::{Config, ConfigMission, ConfigOutpost, Permission};
+use crate::game::outpost::OutpostOwner;
+use crate::game::round::RoundPhase;
+use crate::game::score::ScorePart;
+use crate::game::trade::GameTrade;
 use crate::game::types::*;
 use crate::types::{Amount, ItemRef};
 
@@ -13,6 +17,9 @@ use crate::types::{Amount, ItemRef};
 // TODO: add admin boolean property
 #[derive(Serialize, Debug)]
 pub struct ClientSession {
+    /// Game ID.
+    pub game: String,
+
     /// Account display name.
     pub name: String,
 
@@ -24,6 +31,12 @@ pub struct ClientSession {
 
     /// Whether user has permission to administer the game.
     pub role_admin: bool,
+
+    /// Whether user has permission to referee the game.
+    pub role_referee: bool,
+
+    /// IDs of outposts the user may show the code of.
+    pub outposts: Vec<u32>,
 }
 
 impl ClientSession {
@@ -32,14 +45,63 @@ impl ClientSession {
         let user = config.user(session.user_id)?;
 
         Some(Self {
+            game: session.game.clone(),
             name: config.user(session.user_id).unwrap().name.clone(),
             user_id: session.user_id,
-            role_game: user.role_game,
-            role_admin: user.role_admin,
+            role_game: user.can(Permission::Play),
+            role_admin: user.can(Permission::Admin),
+            role_referee: user.can(Permission::Referee),
+            outposts: user.outposts(),
         })
     }
 }
 
+/// A session as listed to admins, without its token.
+#[derive(Serialize, Debug)]
+pub struct ClientUserSession {
+    /// Public session ID.
+    pub id: String,
+
+    /// User ID.
+    pub user_id: u32,
+
+    /// User display name.
+    pub user_name: String,
+
+    /// Device the session is used on, such as its user agent.
+    pub device: Option<String>,
+
+    /// Creation time, in seconds since the UNIX epoch.
+    pub created: u64,
+
+    /// Last time the session was used, in seconds since the UNIX epoch.
+    pub last_seen: u64,
+
+    /// Expiry time, in seconds since the UNIX epoch.
+    pub expires: Option<u64>,
+
+    /// Number of currently connected clients using this session.
+    pub clients: usize,
+}
+
+impl ClientUserSession {
+    pub fn from_session(config: &Config, clients: &ClientManager, session: &Session) -> Self {
+        Self {
+            id: session.id.clone(),
+            user_id: session.user_id,
+            user_name: config
+                .user(session.user_id)
+                .map(|u| u.name.clone())
+                .unwrap_or_default(),
+            device: session.device.clone(),
+            created: session.created,
+            last_seen: session.last_seen,
+            expires: session.expires,
+            clients: clients.session_client_count(&session.id),
+        }
+    }
+}
+
 /// Represents a user.
 #[derive(Serialize, Debug)]
 pub struct ClientUser {
@@ -183,6 +245,12 @@ pub struct ClientUserStats {
 
     /// Energy earned by user from scanning codes.
     energy_earned: u64,
+
+    /// Number of trades completed by user.
+    trade_count: u32,
+
+    /// Number of missions completed by user.
+    mission_count: u32,
 }
 
 impl ClientUserStats {
@@ -198,25 +266,213 @@ impl ClientUserStats {
             money_earned: game.money_earned.load(Ordering::Relaxed),
             energy_spent: game.energy_spent.load(Ordering::Relaxed),
             energy_earned: game.energy_earned.load(Ordering::Relaxed),
+            trade_count: game.trade_count.load(Ordering::Relaxed),
+            mission_count: game.mission_count.load(Ordering::Relaxed),
         }
     }
 }
 
 /// Client leaderboard user.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ClientLeaderboardUser {
     /// Account display name.
     pub name: String,
 
     /// User money.
     pub money: u64,
+
+    /// User score, to rank by.
+    #[serde(default)]
+    pub score: f64,
+
+    /// Points scored for each property that counts towards the score.
+    #[serde(default)]
+    pub breakdown: Vec<ScorePart>,
 }
 
 impl ClientLeaderboardUser {
-    pub fn from_game(game: &GameUser) -> Result<Self, ()> {
+    pub fn from_game(config: &Config, game: &GameUser) -> Result<Self, ()> {
+        let (score, breakdown) = crate::game::score::score(config, game);
         Ok(Self {
             name: game.config.as_ref().ok_or(())?.name.clone(),
             money: game.inventory.money,
+            score,
+            breakdown,
+        })
+    }
+}
+
+/// Client timed round state.
+#[derive(Serialize, Debug)]
+pub struct ClientRound {
+    /// Round phase.
+    pub phase: RoundPhase,
+
+    /// Seconds until the scheduled start, while waiting for it.
+    pub starts_in: Option<u64>,
+
+    /// Seconds of playing time left, if the round has an end.
+    pub remaining: Option<u64>,
+}
+
+/// Client trade offer.
+#[derive(Serialize, Debug)]
+pub struct ClientTrade {
+    /// Trade ID.
+    pub id: u32,
+
+    /// Offering user ID.
+    pub from: u32,
+
+    /// Offering user display name.
+    pub from_name: String,
+
+    /// Receiving user ID.
+    pub to: u32,
+
+    /// Receiving user display name.
+    pub to_name: String,
+
+    /// Offered items.
+    pub items: Vec<ClientItem>,
+
+    /// Offered money.
+    pub money: u64,
+
+    /// Offered energy.
+    pub energy: u64,
+
+    /// Amounts requested in return.
+    pub request: Vec<Amount>,
+}
+
+impl ClientTrade {
+    pub fn from_game(config: &Config, game: &GameTrade) -> Result<Self, ()> {
+        Ok(Self {
+            id: game.id,
+            from: game.from,
+            from_name: config.user(game.from).ok_or(())?.name.clone(),
+            to: game.to,
+            to_name: config.user(game.to).ok_or(())?.name.clone(),
+            items: game
+                .items
+                .iter()
+                .map(|i| ClientItem::from_game(&i.item))
+                .collect::<Result<_, _>>()?,
+            money: game.money,
+            energy: game.energy,
+            request: game.request.clone(),
+        })
+    }
+}
+
+/// Client mission.
+#[derive(Serialize, Debug)]
+pub struct ClientMission {
+    /// Mission ID.
+    pub id: u32,
+
+    /// Mission display name.
+    pub name: String,
+
+    /// Optional: description to render on client.
+    pub description: Option<String>,
+
+    /// Amounts required to complete the mission.
+    pub require: Vec<Amount>,
+
+    /// Amounts rewarded when completing the mission.
+    pub reward: Vec<Amount>,
+
+    /// Optional: game tick from which the mission is no longer available.
+    pub end_tick: Option<u64>,
+
+    /// Whether the user completed this mission.
+    pub completed: bool,
+}
+
+impl ClientMission {
+    pub fn from_config(config: &ConfigMission, completed: bool) -> Self {
+        Self {
+            id: config.id,
+            name: config.name.clone(),
+            description: config.description.clone(),
+            require: config.require.clone(),
+            reward: config.reward.clone(),
+            end_tick: config.end_tick,
+            completed,
+        }
+    }
+
+    /// List missions that are currently available for a user.
+    pub fn list(config: &Config, tick: u64, game: &GameUser) -> Vec<Self> {
+        config
+            .missions
+            .iter()
+            .filter(|m| m.active(tick))
+            .map(|m| Self::from_config(m, game.missions.contains(&m.id)))
+            .collect()
+    }
+}
+
+/// Client outpost, for admins.
+#[derive(Serialize, Debug)]
+pub struct ClientOutpost {
+    /// Outpost ID.
+    pub id: u32,
+
+    /// Outpost display name.
+    pub name: String,
+
+    /// Optional: hint on where to find the outpost.
+    pub hint: Option<String>,
+
+    /// Whether the outpost is enabled.
+    pub enabled: bool,
+
+    /// Whether the outpost is currently within its opening hours.
+    pub open: bool,
+}
+
+impl ClientOutpost {
+    pub fn from_config(config: &ConfigOutpost, enabled: bool, open: bool) -> Self {
+        Self {
+            id: config.id,
+            name: config.name.clone(),
+            hint: config.hint.clone(),
+            enabled,
+            open,
+        }
+    }
+}
+
+/// Owner of a captured outpost.
+#[derive(Serialize, Debug)]
+pub struct ClientOutpostOwner {
+    /// Outpost ID.
+    pub outpost_id: u32,
+
+    /// Outpost display name.
+    pub outpost_name: String,
+
+    /// Owning user ID.
+    pub user_id: u32,
+
+    /// Owning user display name.
+    pub user_name: String,
+
+    /// Game tick the outpost was captured at.
+    pub since: u64,
+}
+
+impl ClientOutpostOwner {
+    pub fn from_game(config: &Config, outpost_id: u32, owner: &OutpostOwner) -> Option<Self> {
+        Some(Self {
+            outpost_id,
+            outpost_name: config.outposts.outpost(outpost_id)?.name.clone(),
+            user_id: owner.user,
+            user_name: config.user(owner.user)?.name.clone(),
+            since: owner.since,
         })
     }
 }
@@ -1,13 +1,16 @@
+pub mod registry;
 pub mod types;
+pub mod validate;
 
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Deserializer};
 
 use crate::types::ItemRef;
 pub use types::*;
+pub use validate::{ConfigError, LoadError};
 
 // TODO: remove this?
 #[derive(Deserialize, Debug)]
@@ -24,15 +27,39 @@ pub struct Config {
     /// Outposts configuration.
     pub outposts: ConfigOutposts,
 
+    /// Session configuration.
+    #[serde(default)]
+    pub sessions: ConfigSessions,
+
+    /// Timed round configuration.
+    #[serde(default)]
+    pub round: ConfigRound,
+
+    /// Leaderboard scoring configuration.
+    #[serde(default)]
+    pub scoring: ConfigScoring,
+
+    /// Public spectator leaderboard configuration.
+    #[serde(default)]
+    pub spectate: ConfigSpectate,
+
     /// Users.
     pub users: Vec<ConfigUser>,
 
     /// User defaults.
     pub defaults: ConfigDefaults,
 
+    /// Missions.
+    #[serde(default)]
+    pub missions: Vec<ConfigMission>,
+
     /// Game items.
     #[serde(deserialize_with = "vec_to_map")]
     pub items: HashMap<ItemRef, ConfigItem>,
+
+    /// Directory item sprites are in, resolved relative to the configuration file when loading.
+    #[serde(skip, default = "default_sprites_dir")]
+    pub sprites_dir: PathBuf,
 }
 
 impl Config {
@@ -41,35 +68,75 @@ impl Config {
         self.users.iter().find(|t| t.id == user_id)
     }
 
+    /// Whether the given user exists and has the given permission.
+    pub fn user_can(&self, user_id: u32, permission: Permission) -> bool {
+        self.user(user_id)
+            .map(|u| u.can(permission))
+            .unwrap_or(false)
+    }
+
     /// Get item by reference.
     ///
     /// Returns `None` if it doesn't exist.
     pub fn item(&self, item_ref: &ItemRef) -> Option<&ConfigItem> {
         self.items.get(item_ref)
     }
+
+    /// Get a mission by ID.
+    pub fn mission(&self, mission_id: u32) -> Option<&ConfigMission> {
+        self.missions.iter().find(|m| m.id == mission_id)
+    }
 }
 
 /// Load config from disk.
-// TODO: expose errors through error enum
-pub fn load() -> Result<Config, ()> {
+///
+/// The configuration is fully validated, all problems found are reported in the error.
+pub fn load(path: &Path) -> Result<Config, LoadError> {
     debug!("Loading game configuration...");
 
-    let path = PathBuf::from(crate::CONFIG_PATH);
+    let path = path.to_path_buf();
+
+    let data = fs::read(&path).map_err(|err| LoadError::Read(path.clone(), err))?;
 
-    let data = fs::read(path).expect("failed to read config.toml");
+    let mut config: Config =
+        toml::from_slice(&data).map_err(|err| LoadError::Parse(path.clone(), err))?;
 
-    let config = toml::from_slice(&data).expect("failed to parse config.toml");
+    // Find sprites relative to the configuration, not the working directory
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    config.sprites_dir = dir.join(crate::SPRITES_PATH);
+
+    // Validate, item duplicates are lost in the item map so find them separately
+    debug!("Validating game configuration...");
+    let mut errors = match config.validate() {
+        Ok(()) => vec![],
+        Err(errors) => errors,
+    };
+    if let Ok(items) = toml::from_slice::<validate::RawItems>(&data) {
+        errors.extend(items.duplicates());
+    }
+    if !errors.is_empty() {
+        return Err(LoadError::Invalid(path, errors));
+    }
 
-    // TODO: validate config
-    // TODO: - ensure unique IDs
-    // TODO: - ensure sprite paths a correct
-    // TODO: - call item.validate();
+    // Warn about passwords that are still stored in plaintext
+    for user in config.users.iter().filter(|u| u.has_plaintext_password()) {
+        warn!(
+            "User {} has a plaintext password in {}, replace it with a hash from 'mms hash-password'",
+            user.id,
+            path.display()
+        );
+    }
 
     info!("Game configuration loaded");
 
     Ok(config)
 }
 
+/// Default sprites directory, relative to the working directory.
+fn default_sprites_dir() -> PathBuf {
+    PathBuf::from(crate::SPRITES_PATH)
+}
+
 /// Deserialize a `Vec` into a `HashMap` by key.
 fn vec_to_map<'de, D>(d: D) -> Result<HashMap<ItemRef, ConfigItem>, D::Error>
 where
@@ -0,0 +1,98 @@
+//! Game registry configuration.
+//!
+//! Lists multiple games to host from a single server, each with its own game configuration.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::{ConfigError, LoadError};
+
+/// ID of the game when hosting a single game without registry.
+pub const DEFAULT_GAME_ID: &str = "default";
+
+/// Game registry.
+#[derive(Deserialize, Debug)]
+pub struct Registry {
+    /// Games to host.
+    pub games: Vec<RegistryGame>,
+}
+
+/// A game in the registry.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RegistryGame {
+    /// Unique game ID, used in save paths and to bind sessions.
+    pub id: String,
+
+    /// Optional: display name, shown when logging in.
+    pub name: Option<String>,
+
+    /// Game configuration file path, relative to the registry file.
+    pub config: PathBuf,
+}
+
+impl RegistryGame {
+    /// Single default game, used when not hosting from a registry.
+    pub fn single(config: PathBuf) -> Self {
+        Self {
+            id: DEFAULT_GAME_ID.into(),
+            name: None,
+            config,
+        }
+    }
+
+    /// Game display name.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Load game registry from disk.
+///
+/// Game configuration paths are resolved relative to the registry file.
+pub fn load(path: &Path) -> Result<Registry, LoadError> {
+    debug!("Loading game registry...");
+
+    let data = fs::read(path).map_err(|err| LoadError::Read(path.into(), err))?;
+    let mut registry: Registry =
+        toml::from_slice(&data).map_err(|err| LoadError::Parse(path.into(), err))?;
+
+    // Validate game IDs, they're used in paths
+    let mut ids = HashSet::new();
+    let mut errors = vec![];
+    for game in &registry.games {
+        let valid = !game.id.is_empty()
+            && game
+                .id
+                .chars()
+                .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '-' | '_'));
+        if !valid {
+            errors.push(ConfigError::InvalidGameId {
+                game: game.id.clone(),
+            });
+        }
+        if !ids.insert(&game.id) {
+            errors.push(ConfigError::DuplicateGame {
+                game: game.id.clone(),
+            });
+        }
+    }
+    if registry.games.is_empty() {
+        errors.push(ConfigError::NoGames);
+    }
+    if !errors.is_empty() {
+        return Err(LoadError::Invalid(path.into(), errors));
+    }
+
+    // Resolve config paths
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for game in registry.games.iter_mut() {
+        game.config = dir.join(&game.config);
+    }
+
+    info!("Game registry loaded, {} game(s)", registry.games.len());
+
+    Ok(registry)
+}
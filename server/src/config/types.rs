@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use super::validate::{ConfigError, CHANCE_EPSILON};
 use crate::config::Config;
 use crate::types::{Amount, ItemRef};
 use crate::util::{i_to_xy, one, xy_to_i};
@@ -16,6 +21,192 @@ pub struct ConfigGame {
 
     /// Whether to immediately start new games.
     pub start: bool,
+
+    /// Maximum number of missed ticks to catch up at once, such as after a restart.
+    #[serde(default = "default_max_catch_up_ticks")]
+    pub max_catch_up_ticks: u64,
+
+    /// Optional: seed for the game random source, random if not set.
+    ///
+    /// With the same seed and sequence of actions, a game is fully reproducible.
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Number of ticks between leaderboard history snapshots, `0` to disable.
+    #[serde(default = "default_history_interval_ticks")]
+    pub history_interval_ticks: u64,
+}
+
+/// Default maximum number of missed ticks to catch up at once.
+fn default_max_catch_up_ticks() -> u64 {
+    300
+}
+
+/// Default number of ticks between leaderboard history snapshots.
+fn default_history_interval_ticks() -> u64 {
+    60
+}
+
+/// Session configuration.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ConfigSessions {
+    /// Optional: hours after login a session expires.
+    #[serde(default)]
+    pub max_age_hours: Option<u64>,
+
+    /// Optional: hours a session may be unused before it expires.
+    #[serde(default)]
+    pub max_idle_hours: Option<u64>,
+}
+
+impl ConfigSessions {
+    /// Maximum session age.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_hours.map(|h| Duration::from_secs(h * 3600))
+    }
+
+    /// Maximum session idle time.
+    pub fn max_idle(&self) -> Option<Duration> {
+        self.max_idle_hours.map(|h| Duration::from_secs(h * 3600))
+    }
+}
+
+/// Timed round configuration.
+///
+/// Without any of these, the game runs until an admin pauses it.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ConfigRound {
+    /// Optional: time to automatically start the game at, in RFC 3339 format.
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// Optional: playing time after which the game ends, such as `2h 30m`.
+    ///
+    /// Converted into an end tick, so pauses are not counted.
+    #[serde(default)]
+    pub duration: Option<String>,
+
+    /// Optional: game tick after which the game ends, instead of a duration.
+    #[serde(default)]
+    pub end_tick: Option<u64>,
+
+    /// Time windows in which the game is automatically paused.
+    #[serde(default)]
+    pub pauses: Vec<ConfigWindow>,
+}
+
+impl ConfigRound {
+    /// Scheduled start time in seconds since the UNIX epoch, if set.
+    pub fn start_time(&self) -> Option<u64> {
+        self.start.as_deref().and_then(parse_time)
+    }
+
+    /// Game tick at which the game ends, if set.
+    pub fn end_tick(&self, tick_millis: u64) -> Option<u64> {
+        let duration = self.duration.as_deref().and_then(parse_duration);
+        self.end_tick
+            .or_else(|| duration.map(|d| d.as_millis() as u64 / tick_millis))
+    }
+
+    /// Whether the given time in seconds since the UNIX epoch is in a pause window.
+    pub fn paused_at(&self, now: u64) -> bool {
+        self.pauses.iter().any(|pause| pause.contains(now))
+    }
+}
+
+/// A time window, such as a pause or outpost opening hours.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConfigWindow {
+    /// Start time, in RFC 3339 format.
+    pub start: String,
+
+    /// End time, in RFC 3339 format.
+    pub end: String,
+}
+
+impl ConfigWindow {
+    /// Whether the given time in seconds since the UNIX epoch is in this window.
+    pub fn contains(&self, now: u64) -> bool {
+        match (parse_time(&self.start), parse_time(&self.end)) {
+            (Some(start), Some(end)) => start <= now && now < end,
+            _ => false,
+        }
+    }
+}
+
+/// Parse an RFC 3339 time into seconds since the UNIX epoch.
+pub(super) fn parse_time(time: &str) -> Option<u64> {
+    let time = humantime::parse_rfc3339_weak(time).ok()?;
+    time.duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Parse a human readable duration, such as `2h 30m`.
+pub(super) fn parse_duration(duration: &str) -> Option<Duration> {
+    humantime::parse_duration(duration).ok()
+}
+
+/// Leaderboard scoring configuration.
+///
+/// The score is the sum of each property multiplied by its weight.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigScoring {
+    /// Weight of money.
+    pub money: f64,
+
+    /// Weight of energy.
+    pub energy: f64,
+
+    /// Weight of the summed sell value of all held items.
+    pub item_value: f64,
+
+    /// Weight of the number of discovered items.
+    pub discovered: f64,
+
+    /// Weights of user stats, by stat name.
+    pub stats: HashMap<String, f64>,
+}
+
+impl Default for ConfigScoring {
+    fn default() -> Self {
+        Self {
+            money: 1.0,
+            energy: 0.0,
+            item_value: 0.0,
+            discovered: 0.0,
+            stats: HashMap::new(),
+        }
+    }
+}
+
+/// Public spectator leaderboard configuration.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigSpectate {
+    /// Whether unauthenticated displays may spectate the leaderboard.
+    pub enabled: bool,
+
+    /// Whether to hide user names, showing ranks instead.
+    pub hide_names: bool,
+
+    /// Optional: only show this number of top users.
+    pub top: Option<usize>,
+
+    /// Seconds between leaderboard updates pushed to spectators.
+    pub interval_secs: u64,
+}
+
+impl Default for ConfigSpectate {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hide_names: false,
+            top: None,
+            interval_secs: 5,
+        }
+    }
 }
 
 /// Outposts configuration.
@@ -41,6 +232,154 @@ pub struct ConfigOutposts {
 
     /// Energy multiplier.
     pub energy_multiplier: u64,
+
+    /// Seconds after which a new outpost token is issued.
+    #[serde(default = "default_token_interval_secs")]
+    pub token_interval_secs: u64,
+
+    /// Seconds an outpost token is valid for, from the start of its interval.
+    #[serde(default = "default_token_valid_secs")]
+    pub token_valid_secs: u64,
+
+    /// Whether each outpost token can only be redeemed once by each user.
+    #[serde(default)]
+    pub single_use: bool,
+
+    /// Configured outposts.
+    ///
+    /// Outpost IDs that aren't listed are rejected.
+    #[serde(default)]
+    pub list: Vec<ConfigOutpost>,
+
+    /// Whether to accept any outpost ID while no outposts are listed, rewarded with the money
+    /// and energy above.
+    #[serde(default)]
+    pub allow_unlisted: bool,
+}
+
+/// Default seconds after which a new outpost token is issued.
+fn default_token_interval_secs() -> u64 {
+    30
+}
+
+/// Default seconds an outpost token is valid for.
+fn default_token_valid_secs() -> u64 {
+    90
+}
+
+impl ConfigOutposts {
+    /// Get an outpost by ID.
+    pub fn outpost(&self, outpost_id: u32) -> Option<&ConfigOutpost> {
+        self.list.iter().find(|o| o.id == outpost_id)
+    }
+
+    /// Whether the given outpost ID is known.
+    ///
+    /// Only listed outposts are known, unless any ID is accepted.
+    pub fn known(&self, outpost_id: u32) -> bool {
+        self.any() || self.outpost(outpost_id).is_some()
+    }
+
+    /// Whether any outpost ID is accepted, because none are listed and unlisted ones are allowed.
+    pub fn any(&self) -> bool {
+        self.list.is_empty() && self.allow_unlisted
+    }
+}
+
+/// Outpost configuration.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConfigOutpost {
+    /// Unique outpost ID.
+    pub id: u32,
+
+    /// Outpost display name.
+    pub name: String,
+
+    /// Optional: hint on where to find the outpost.
+    #[serde(default)]
+    pub hint: Option<String>,
+
+    /// Whether the outpost can be scanned, admins may toggle this while playing.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Amounts rewarded when scanning, money and energy from `[outposts]` if empty.
+    #[serde(default)]
+    pub reward: Vec<Amount>,
+
+    /// Whether to also reward money and energy from `[outposts]` on top of `reward`.
+    #[serde(default)]
+    pub bonus: bool,
+
+    /// Optional: number of ticks before the same user may scan the outpost again.
+    ///
+    /// Without a cooldown, users can't scan the same outpost twice in a row.
+    #[serde(default)]
+    pub cooldown_ticks: Option<u64>,
+
+    /// Optional: cooldown as duration, such as `5m`, instead of ticks.
+    #[serde(default)]
+    pub cooldown: Option<String>,
+
+    /// Time windows in which the outpost is open, always open if empty.
+    #[serde(default)]
+    pub hours: Vec<ConfigWindow>,
+
+    /// Whether to accept static codes that never expire, such as printed on a sheet.
+    #[serde(default)]
+    pub static_code: bool,
+
+    /// Whether scanning the outpost captures it for the scanning user.
+    #[serde(default)]
+    pub capture: bool,
+
+    /// Optional: number of ticks after a capture before another user may steal the outpost.
+    #[serde(default)]
+    pub steal_cooldown_ticks: Option<u64>,
+
+    /// Optional: steal cooldown as duration, such as `5m`, instead of ticks.
+    #[serde(default)]
+    pub steal_cooldown: Option<String>,
+
+    /// Amounts the owner of a captured outpost earns every income interval.
+    #[serde(default)]
+    pub income: Vec<Amount>,
+
+    /// Number of ticks between paying income to the owner.
+    #[serde(default = "default_income_interval_ticks")]
+    pub income_interval_ticks: u64,
+}
+
+/// Default number of ticks between paying outpost income.
+fn default_income_interval_ticks() -> u64 {
+    1
+}
+
+impl ConfigOutpost {
+    /// Cooldown in game ticks, if set.
+    pub fn cooldown_ticks(&self, tick_millis: u64) -> Option<u64> {
+        let cooldown = self.cooldown.as_deref().and_then(parse_duration);
+        self.cooldown_ticks
+            .or_else(|| cooldown.map(|d| d.as_millis() as u64 / tick_millis))
+    }
+
+    /// Steal cooldown in game ticks, zero if not set.
+    pub fn steal_cooldown_ticks(&self, tick_millis: u64) -> u64 {
+        let cooldown = self.steal_cooldown.as_deref().and_then(parse_duration);
+        self.steal_cooldown_ticks
+            .or_else(|| cooldown.map(|d| d.as_millis() as u64 / tick_millis))
+            .unwrap_or(0)
+    }
+
+    /// Whether the outpost is open at the given time in seconds since the UNIX epoch.
+    pub fn open_at(&self, now: u64) -> bool {
+        self.hours.is_empty() || self.hours.iter().any(|window| window.contains(now))
+    }
+}
+
+/// Produces `true`.
+fn default_true() -> bool {
+    true
 }
 
 /// Represents a configured user.
@@ -51,15 +390,87 @@ pub struct ConfigUser {
     pub id: u32,
 
     /// Whether user has permission to play the game.
+    #[serde(default)]
     pub role_game: bool,
 
     /// Whether user has permission to administer the game.
     #[serde(default)]
     pub role_admin: bool,
+
+    /// Additional roles, such as `referee` or `outpost:3`.
+    #[serde(default)]
+    pub roles: Vec<String>,
     pub name: String,
+
+    /// User login password, an argon2 hash in PHC string format or plaintext.
     pub password: String,
 }
 
+impl ConfigUser {
+    /// Verify the given login password.
+    pub fn verify_password(&self, password: &str) -> bool {
+        crate::auth::verify_password(&self.password, password)
+    }
+
+    /// Whether the configured password is stored in plaintext.
+    pub fn has_plaintext_password(&self) -> bool {
+        !crate::auth::is_password_hash(&self.password)
+    }
+}
+
+/// A permission, granted to users through their roles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Play the game, by `role_game` or the `game` role.
+    Play,
+
+    /// Administer the game, by `role_admin` or the `admin` role.
+    Admin,
+
+    /// Referee the game, such as sanctioning or rewarding users, by the `referee` role or admin.
+    Referee,
+
+    /// Show the code of an outpost, by the `outpost:<id>` role or referee.
+    Outpost(u32),
+}
+
+impl Permission {
+    /// Whether the given role name is known.
+    pub fn known_role(role: &str) -> bool {
+        match role.strip_prefix("outpost:") {
+            Some(id) => id.parse::<u32>().is_ok(),
+            None => matches!(role, "game" | "admin" | "referee"),
+        }
+    }
+}
+
+impl ConfigUser {
+    /// Whether the user has the given role.
+    fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Whether the user has the given permission.
+    pub fn can(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Play => self.role_game || self.has_role("game"),
+            Permission::Admin => self.role_admin || self.has_role("admin"),
+            Permission::Referee => self.can(Permission::Admin) || self.has_role("referee"),
+            Permission::Outpost(id) => {
+                self.can(Permission::Referee) || self.has_role(&format!("outpost:{}", id))
+            }
+        }
+    }
+
+    /// IDs of outposts the user may show the code of, by an `outpost:<id>` role.
+    pub fn outposts(&self) -> Vec<u32> {
+        self.roles
+            .iter()
+            .filter_map(|role| role.strip_prefix("outpost:")?.parse().ok())
+            .collect()
+    }
+}
+
 /// User defaults.
 #[derive(Deserialize, Debug, Clone)]
 pub struct ConfigDefaults {
@@ -76,6 +487,46 @@ pub struct ConfigDefaults {
     pub merge_cost: Option<Vec<Amount>>,
 }
 
+/// Mission configuration.
+///
+/// A mission requires users to deliver a set of amounts, and rewards them with other amounts.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConfigMission {
+    /// Unique mission ID.
+    pub id: u32,
+
+    /// Mission display name.
+    pub name: String,
+
+    /// Optional: description to render on client.
+    pub description: Option<String>,
+
+    /// Amounts required to complete the mission, consumed on submission.
+    pub require: Vec<Amount>,
+
+    /// Amounts rewarded when completing the mission.
+    pub reward: Vec<Amount>,
+
+    /// Optional: game tick from which the mission is available.
+    pub start_tick: Option<u64>,
+
+    /// Optional: game tick from which the mission is no longer available.
+    pub end_tick: Option<u64>,
+}
+
+impl ConfigMission {
+    /// Check whether this mission is available at the given game tick.
+    pub fn active(&self, tick: u64) -> bool {
+        self.start_tick.map(|t| t <= tick).unwrap_or(true)
+            && self.end_tick.map(|t| tick < t).unwrap_or(true)
+    }
+
+    /// Check whether this mission becomes available or unavailable at the given game tick.
+    pub fn changes_at(&self, tick: u64) -> bool {
+        self.start_tick == Some(tick) || self.end_tick == Some(tick)
+    }
+}
+
 /// Game item configuration.
 // TODO: do not clone
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -129,23 +580,96 @@ pub struct ConfigItem {
 
 impl ConfigItem {
     /// Validate correctness.
-    pub fn validate(&self, config: &Config) -> Result<(), ()> {
-        // TODO: validate item is correct
-        // TODO: - unique ID
-        // TODO: - merge ID okay
-        // TODO: - no empty tier name / name / label
-        // TODO: - no drop interval for no drops
-        // TODO: - any drops if drop interval is convered
-        // TODO: - drop item IDs
-        // TODO: - sprite path must exist
-        Ok(())
+    ///
+    /// Returns a list of all problems found with this item.
+    pub fn validate(&self, config: &Config) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+        let item = || self.id.clone();
+
+        if self.id.tier_level().is_none() {
+            errors.push(ConfigError::InvalidItemRef { item: item() });
+        }
+
+        // Display properties must not be empty
+        let fields = [
+            ("name", Some(&self.name)),
+            ("tier", Some(&self.tier)),
+            ("label", self.label.as_ref()),
+        ];
+        for (field, value) in fields.iter() {
+            if matches!(value, Some(value) if value.trim().is_empty()) {
+                errors.push(ConfigError::EmptyItemField {
+                    item: item(),
+                    field,
+                });
+            }
+        }
+
+        // Merge target must exist
+        if let Some(merge) = &self.merge {
+            if merge == &self.id {
+                errors.push(ConfigError::MergeIntoSelf { item: item() });
+            } else if config.item(merge).is_none() {
+                errors.push(ConfigError::UnknownMergeItem {
+                    item: item(),
+                    merge: merge.clone(),
+                });
+            }
+        }
+
+        // Drops must exist and have sane chances, drops and interval must be set together
+        for drop in &self.drops {
+            if config.item(&drop.item).is_none() {
+                errors.push(ConfigError::UnknownDropItem {
+                    item: item(),
+                    drop: drop.item.clone(),
+                });
+            }
+            if !(drop.chance > 0.0 && drop.chance <= 1.0) {
+                errors.push(ConfigError::InvalidDropChance {
+                    item: item(),
+                    drop: drop.item.clone(),
+                });
+            }
+        }
+        let sum = self.drops.iter().map(|d| d.chance).sum::<f64>();
+        if sum > 1.0 + CHANCE_EPSILON {
+            errors.push(ConfigError::DropChanceSum { item: item(), sum });
+        }
+        match (self.drop_interval, self.drops.is_empty()) {
+            (Some(_), true) => errors.push(ConfigError::DropIntervalWithoutDrops { item: item() }),
+            (None, false) => errors.push(ConfigError::DropsWithoutInterval { item: item() }),
+            _ => {}
+        }
+
+        // Costs must reference existing items
+        if let Some(buy) = &self.buy {
+            config.validate_amounts(&format!("item {}: buy", self.id), buy, &mut errors);
+        }
+        if let Some(cost) = &self.merge_cost {
+            let context = format!("item {}: merge_cost", self.id);
+            config.validate_amounts(&context, cost, &mut errors);
+        }
+
+        // Sprite must exist
+        if !config.sprites_dir.join(&self.sprite_path).is_file() {
+            errors.push(ConfigError::MissingSprite {
+                item: item(),
+                path: self.sprite_path.clone(),
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     /// Select a random drop.
     ///
     /// This takes chance configuration into account.
-    pub fn random_drop(&self) -> Option<ItemRef> {
-        let mut rng = rand::thread_rng();
+    pub fn random_drop(&self, rng: &mut impl Rng) -> Option<ItemRef> {
         let total = self.drops.iter().map(|d| d.chance).sum::<f64>();
         let mut value = rng.gen::<f64>();
 
@@ -0,0 +1,582 @@
+//! Configuration validation.
+//!
+//! Collects every problem in a loaded configuration, so item designers can fix all of them at
+//! once rather than finding them one by one while playing.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use super::{Config, ConfigItem, Permission};
+use crate::game::GameUserStats;
+use crate::types::{Amount, ItemRef};
+
+/// Maximum allowed deviation when summing drop chances.
+pub(super) const CHANCE_EPSILON: f64 = 1e-9;
+
+/// Error loading the configuration.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Failed to read configuration file.
+    Read(PathBuf, io::Error),
+
+    /// Failed to parse configuration file.
+    Parse(PathBuf, toml::de::Error),
+
+    /// Configuration is invalid, lists all problems.
+    Invalid(PathBuf, Vec<ConfigError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Read(path, err) => {
+                write!(f, "failed to read config {}: {}", path.display(), err)
+            }
+            LoadError::Parse(path, err) => {
+                write!(f, "failed to parse config {}: {}", path.display(), err)
+            }
+            LoadError::Invalid(path, errors) => {
+                write!(
+                    f,
+                    "invalid config {}, found {} problem(s):",
+                    path.display(),
+                    errors.len()
+                )?;
+                for err in errors {
+                    write!(f, "\n  - {}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A problem in the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// Game tick duration must be larger than zero.
+    ZeroTickMillis,
+
+    /// Minimum outpost reward is larger than its maximum.
+    OutpostRewardRange { kind: &'static str },
+
+    /// Outpost token interval is zero, or longer than tokens are valid.
+    OutpostTokenInterval,
+
+    /// Time is not in RFC 3339 format.
+    InvalidTime { field: String },
+
+    /// Round duration can't be parsed, or is zero.
+    InvalidRoundDuration,
+
+    /// Round has both a duration and end tick.
+    RoundDurationAndEndTick,
+
+    /// Round pause window ends before it starts.
+    PauseWindow { index: usize },
+
+    /// Scoring weight is not a finite number.
+    InvalidScoringWeight { name: String },
+
+    /// Scoring references a stat that doesn't exist.
+    UnknownScoringStat { stat: String },
+
+    /// Multiple users share the same ID.
+    DuplicateUser { user: u32 },
+
+    /// User has no display name.
+    EmptyUserName { user: u32 },
+
+    /// User has a role that doesn't exist.
+    UnknownRole { user: u32, role: String },
+
+    /// User password hash can't be parsed.
+    InvalidPasswordHash { user: u32 },
+
+    /// Multiple items share the same reference.
+    DuplicateItem { item: ItemRef },
+
+    /// Item reference is not in `tier.level` format.
+    InvalidItemRef { item: ItemRef },
+
+    /// Item has an empty display property.
+    EmptyItemField { item: ItemRef, field: &'static str },
+
+    /// Item merges into an item that doesn't exist.
+    UnknownMergeItem { item: ItemRef, merge: ItemRef },
+
+    /// Item merges into itself.
+    MergeIntoSelf { item: ItemRef },
+
+    /// Item drops an item that doesn't exist.
+    UnknownDropItem { item: ItemRef, drop: ItemRef },
+
+    /// Item has a drop with an invalid chance.
+    InvalidDropChance { item: ItemRef, drop: ItemRef },
+
+    /// Drop chances of item sum to more than one.
+    DropChanceSum { item: ItemRef, sum: f64 },
+
+    /// Item has a drop interval, but no drops.
+    DropIntervalWithoutDrops { item: ItemRef },
+
+    /// Item has drops, but no drop interval.
+    DropsWithoutInterval { item: ItemRef },
+
+    /// Item sprite file does not exist.
+    MissingSprite { item: ItemRef, path: String },
+
+    /// An amount references an item that doesn't exist.
+    UnknownAmountItem { context: String, item: ItemRef },
+
+    /// An amount has an item quantity of zero.
+    ZeroAmountQuantity { context: String, item: ItemRef },
+
+    /// Default inventory references an item that doesn't exist.
+    UnknownDefaultItem { item: ItemRef },
+
+    /// Default inventory has more items than fit the inventory grid.
+    TooManyDefaultItems { count: usize },
+
+    /// Multiple outposts share the same ID.
+    DuplicateOutpost { outpost: u32 },
+
+    /// Outpost opening hours window ends before it starts.
+    OutpostWindow { outpost: u32, index: usize },
+
+    /// Outpost cooldown can't be parsed.
+    InvalidOutpostCooldown { outpost: u32, field: &'static str },
+
+    /// Outpost has both a cooldown and cooldown ticks.
+    OutpostCooldownAndTicks { outpost: u32, field: &'static str },
+
+    /// Outpost income interval is zero.
+    OutpostIncomeInterval { outpost: u32 },
+
+    /// Multiple missions share the same ID.
+    DuplicateMission { mission: u32 },
+
+    /// Mission ends before it starts.
+    MissionWindow { mission: u32 },
+
+    /// Game registry lists no games.
+    NoGames,
+
+    /// Multiple games in registry share the same ID.
+    DuplicateGame { game: String },
+
+    /// Game ID in registry has invalid characters.
+    InvalidGameId { game: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ConfigError::*;
+        match self {
+            ZeroTickMillis => write!(f, "game.tick_millis must be larger than 0"),
+            OutpostRewardRange { kind } => write!(
+                f,
+                "outposts.{0}_min must not be larger than outposts.{0}_max",
+                kind
+            ),
+            OutpostTokenInterval => write!(
+                f,
+                "outposts.token_interval_secs must be larger than 0, and not larger than outposts.token_valid_secs"
+            ),
+            InvalidTime { field } => {
+                write!(f, "{}: must be a time in RFC 3339 format", field)
+            }
+            InvalidRoundDuration => write!(
+                f,
+                "round.duration: must be a duration larger than 0, such as '2h 30m'"
+            ),
+            RoundDurationAndEndTick => {
+                write!(f, "round: must not set both duration and end_tick")
+            }
+            PauseWindow { index } => {
+                write!(f, "round.pauses[{}]: end must be after start", index)
+            }
+            InvalidScoringWeight { name } => {
+                write!(f, "scoring.{}: weight must be a finite number", name)
+            }
+            UnknownScoringStat { stat } => {
+                write!(f, "scoring.stats: references unknown stat {}", stat)
+            }
+            DuplicateUser { user } => write!(f, "user {}: ID is used more than once", user),
+            EmptyUserName { user } => write!(f, "user {}: name must not be empty", user),
+            UnknownRole { user, role } => write!(
+                f,
+                "user {}: unknown role '{}', must be game, admin, referee or outpost:<id>",
+                user, role
+            ),
+            InvalidPasswordHash { user } => {
+                write!(f, "user {}: password hash is not a valid PHC string", user)
+            }
+            DuplicateItem { item } => write!(f, "item {}: ref is used more than once", item),
+            InvalidItemRef { item } => {
+                write!(f, "item {}: ref must be in 'tier.level' format", item)
+            }
+            EmptyItemField { item, field } => {
+                write!(f, "item {}: {} must not be empty", item, field)
+            }
+            UnknownMergeItem { item, merge } => {
+                write!(f, "item {}: merges into unknown item {}", item, merge)
+            }
+            MergeIntoSelf { item } => write!(f, "item {}: merges into itself", item),
+            UnknownDropItem { item, drop } => {
+                write!(f, "item {}: drops unknown item {}", item, drop)
+            }
+            InvalidDropChance { item, drop } => write!(
+                f,
+                "item {}: drop chance of {} must be between 0 and 1",
+                item, drop
+            ),
+            DropChanceSum { item, sum } => write!(
+                f,
+                "item {}: drop chances sum to {}, must not exceed 1",
+                item, sum
+            ),
+            DropIntervalWithoutDrops { item } => {
+                write!(f, "item {}: has drop_interval but no drops", item)
+            }
+            DropsWithoutInterval { item } => {
+                write!(f, "item {}: has drops but no drop_interval", item)
+            }
+            MissingSprite { item, path } => {
+                write!(f, "item {}: sprite file {} does not exist", item, path)
+            }
+            UnknownAmountItem { context, item } => {
+                write!(f, "{}: references unknown item {}", context, item)
+            }
+            ZeroAmountQuantity { context, item } => {
+                write!(f, "{}: quantity of item {} must not be 0", context, item)
+            }
+            UnknownDefaultItem { item } => {
+                write!(f, "defaults.inventory: references unknown item {}", item)
+            }
+            TooManyDefaultItems { count } => write!(
+                f,
+                "defaults.inventory: has {} items, but inventory has {} cells",
+                count,
+                crate::INV_SIZE
+            ),
+            DuplicateOutpost { outpost } => {
+                write!(f, "outpost {}: ID is used more than once", outpost)
+            }
+            OutpostWindow { outpost, index } => write!(
+                f,
+                "outpost {}: hours[{}]: end must be after start",
+                outpost, index
+            ),
+            InvalidOutpostCooldown { outpost, field } => write!(
+                f,
+                "outpost {}: {} must be a duration, such as '5m'",
+                outpost, field
+            ),
+            OutpostCooldownAndTicks { outpost, field } => write!(
+                f,
+                "outpost {}: must not set both {1} and {1}_ticks",
+                outpost, field
+            ),
+            OutpostIncomeInterval { outpost } => write!(
+                f,
+                "outpost {}: income_interval_ticks must be larger than 0",
+                outpost
+            ),
+            DuplicateMission { mission } => {
+                write!(f, "mission {}: ID is used more than once", mission)
+            }
+            MissionWindow { mission } => {
+                write!(f, "mission {}: end_tick must be after start_tick", mission)
+            }
+            NoGames => write!(f, "games: must list at least one game"),
+            DuplicateGame { game } => write!(f, "game {}: ID is used more than once", game),
+            InvalidGameId { game } => write!(
+                f,
+                "game {}: ID must only contain lowercase letters, digits, '-' and '_'",
+                game
+            ),
+        }
+    }
+}
+
+/// Minimal view of the configured items.
+///
+/// Used to find duplicate item references, which are lost when building the item map.
+#[derive(Deserialize)]
+pub(super) struct RawItems {
+    items: Vec<RawItem>,
+}
+
+#[derive(Deserialize)]
+struct RawItem {
+    #[serde(rename = "ref")]
+    id: ItemRef,
+}
+
+impl RawItems {
+    /// Find item references that are used more than once.
+    pub(super) fn duplicates(&self) -> Vec<ConfigError> {
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        self.items
+            .iter()
+            .filter(|i| !seen.insert(&i.id) && reported.insert(&i.id))
+            .map(|i| ConfigError::DuplicateItem { item: i.id.clone() })
+            .collect()
+    }
+}
+
+impl Config {
+    /// Validate correctness of the whole configuration.
+    ///
+    /// Returns a list of all problems found.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+
+        // Game and outposts
+        if self.game.tick_millis == 0 {
+            errors.push(ConfigError::ZeroTickMillis);
+        }
+        if self.outposts.money_min > self.outposts.money_max {
+            errors.push(ConfigError::OutpostRewardRange { kind: "money" });
+        }
+        if self.outposts.energy_min > self.outposts.energy_max {
+            errors.push(ConfigError::OutpostRewardRange { kind: "energy" });
+        }
+        if self.outposts.token_interval_secs == 0
+            || self.outposts.token_interval_secs > self.outposts.token_valid_secs
+        {
+            errors.push(ConfigError::OutpostTokenInterval);
+        }
+
+        // Round
+        let round = &self.round;
+        let check_time = |field: String, time: &str, errors: &mut Vec<ConfigError>| {
+            let time = super::types::parse_time(time);
+            if time.is_none() {
+                errors.push(ConfigError::InvalidTime { field });
+            }
+            time
+        };
+        if let Some(start) = &round.start {
+            check_time("round.start".into(), start, &mut errors);
+        }
+        for (index, pause) in round.pauses.iter().enumerate() {
+            let field = format!("round.pauses[{}]", index);
+            let start = check_time(format!("{}.start", field), &pause.start, &mut errors);
+            let end = check_time(format!("{}.end", field), &pause.end, &mut errors);
+            if let (Some(start), Some(end)) = (start, end) {
+                if end <= start {
+                    errors.push(ConfigError::PauseWindow { index });
+                }
+            }
+        }
+        if let Some(duration) = &round.duration {
+            let valid = super::types::parse_duration(duration)
+                .map(|d| d.as_millis() as u64 >= self.game.tick_millis)
+                .unwrap_or(false);
+            if !valid {
+                errors.push(ConfigError::InvalidRoundDuration);
+            }
+            if round.end_tick.is_some() {
+                errors.push(ConfigError::RoundDurationAndEndTick);
+            }
+        }
+
+        // Outposts
+        let mut outpost_ids = HashSet::new();
+        for outpost in &self.outposts.list {
+            if !outpost_ids.insert(outpost.id) {
+                errors.push(ConfigError::DuplicateOutpost {
+                    outpost: outpost.id,
+                });
+            }
+            let cooldowns = [
+                ("cooldown", &outpost.cooldown, outpost.cooldown_ticks),
+                (
+                    "steal_cooldown",
+                    &outpost.steal_cooldown,
+                    outpost.steal_cooldown_ticks,
+                ),
+            ];
+            for (field, cooldown, ticks) in cooldowns {
+                let cooldown = match cooldown {
+                    Some(cooldown) => cooldown,
+                    None => continue,
+                };
+                if super::types::parse_duration(cooldown).is_none() {
+                    errors.push(ConfigError::InvalidOutpostCooldown {
+                        outpost: outpost.id,
+                        field,
+                    });
+                }
+                if ticks.is_some() {
+                    errors.push(ConfigError::OutpostCooldownAndTicks {
+                        outpost: outpost.id,
+                        field,
+                    });
+                }
+            }
+            if outpost.income_interval_ticks == 0 {
+                errors.push(ConfigError::OutpostIncomeInterval {
+                    outpost: outpost.id,
+                });
+            }
+            for (index, window) in outpost.hours.iter().enumerate() {
+                let field = format!("outpost {}: hours[{}]", outpost.id, index);
+                let start = check_time(format!("{}.start", field), &window.start, &mut errors);
+                let end = check_time(format!("{}.end", field), &window.end, &mut errors);
+                if let (Some(start), Some(end)) = (start, end) {
+                    if end <= start {
+                        errors.push(ConfigError::OutpostWindow {
+                            outpost: outpost.id,
+                            index,
+                        });
+                    }
+                }
+            }
+            self.validate_amounts(
+                &format!("outpost {}: reward", outpost.id),
+                &outpost.reward,
+                &mut errors,
+            );
+            self.validate_amounts(
+                &format!("outpost {}: income", outpost.id),
+                &outpost.income,
+                &mut errors,
+            );
+        }
+
+        // Scoring
+        let scoring = &self.scoring;
+        let mut weights = vec![
+            ("money".to_string(), scoring.money),
+            ("energy".to_string(), scoring.energy),
+            ("item_value".to_string(), scoring.item_value),
+            ("discovered".to_string(), scoring.discovered),
+        ];
+        let mut stats: Vec<_> = scoring.stats.iter().collect();
+        stats.sort_unstable_by_key(|(stat, _)| stat.as_str());
+        for (stat, weight) in stats {
+            if !GameUserStats::NAMES.contains(&stat.as_str()) {
+                errors.push(ConfigError::UnknownScoringStat { stat: stat.clone() });
+            }
+            weights.push((format!("stats.{}", stat), *weight));
+        }
+        for (name, weight) in weights {
+            if !weight.is_finite() {
+                errors.push(ConfigError::InvalidScoringWeight { name });
+            }
+        }
+
+        // Users
+        let mut user_ids = HashSet::new();
+        for user in &self.users {
+            if !user_ids.insert(user.id) {
+                errors.push(ConfigError::DuplicateUser { user: user.id });
+            }
+            if user.name.trim().is_empty() {
+                errors.push(ConfigError::EmptyUserName { user: user.id });
+            }
+            for role in user.roles.iter().filter(|r| !Permission::known_role(r)) {
+                errors.push(ConfigError::UnknownRole {
+                    user: user.id,
+                    role: role.clone(),
+                });
+            }
+            if !user.has_plaintext_password()
+                && !crate::auth::is_valid_password_hash(&user.password)
+            {
+                errors.push(ConfigError::InvalidPasswordHash { user: user.id });
+            }
+        }
+
+        // Defaults
+        for item in &self.defaults.inventory {
+            if self.item(item).is_none() {
+                errors.push(ConfigError::UnknownDefaultItem { item: item.clone() });
+            }
+        }
+        if self.defaults.inventory.len() > crate::INV_SIZE as usize {
+            errors.push(ConfigError::TooManyDefaultItems {
+                count: self.defaults.inventory.len(),
+            });
+        }
+        if let Some(cost) = &self.defaults.merge_cost {
+            self.validate_amounts("defaults.merge_cost", cost, &mut errors);
+        }
+
+        // Items, in stable order
+        let mut items: Vec<&ConfigItem> = self.items.values().collect();
+        items.sort_by_key(|i| (i.id.tier_level(), i.id.to_string()));
+        for item in items {
+            if let Err(item_errors) = item.validate(self) {
+                errors.extend(item_errors);
+            }
+        }
+
+        // Missions
+        let mut mission_ids = HashSet::new();
+        for mission in &self.missions {
+            if !mission_ids.insert(mission.id) {
+                errors.push(ConfigError::DuplicateMission {
+                    mission: mission.id,
+                });
+            }
+            if let (Some(start), Some(end)) = (mission.start_tick, mission.end_tick) {
+                if end <= start {
+                    errors.push(ConfigError::MissionWindow {
+                        mission: mission.id,
+                    });
+                }
+            }
+            let context = format!("mission {}", mission.id);
+            self.validate_amounts(
+                &format!("{}: require", context),
+                &mission.require,
+                &mut errors,
+            );
+            self.validate_amounts(
+                &format!("{}: reward", context),
+                &mission.reward,
+                &mut errors,
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validate a list of amounts, ensuring all referenced items exist.
+    pub(super) fn validate_amounts(
+        &self,
+        context: &str,
+        amounts: &[Amount],
+        errors: &mut Vec<ConfigError>,
+    ) {
+        for amount in amounts {
+            if let Amount::Item { item, quantity } = amount {
+                if self.item(item).is_none() {
+                    errors.push(ConfigError::UnknownAmountItem {
+                        context: context.into(),
+                        item: item.clone(),
+                    });
+                }
+                if *quantity == 0 {
+                    errors.push(ConfigError::ZeroAmountQuantity {
+                        context: context.into(),
+                        item: item.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
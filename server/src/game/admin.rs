@@ -0,0 +1,98 @@
+//! Admin inventory editing.
+//!
+//! Admins may fix a user inventory, such as to compensate a team after a device glitch. Every
+//! edit is journaled along with the admin that made it.
+
+use serde::{Deserialize, Serialize};
+
+use super::journal::JournalEventKind;
+use super::{Game, GameItem};
+use crate::client::ClientInventory;
+use crate::config::{Config, Permission};
+use crate::types::ItemRef;
+
+/// An admin edit of a user inventory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InventoryEdit {
+    /// Place an item at a cell, replacing any item there, or remove the item if `None`.
+    SetItem { cell: u8, item: Option<ItemRef> },
+
+    /// Set money and energy balances, unchanged if `None`.
+    SetBalance {
+        #[serde(default)]
+        money: Option<u64>,
+        #[serde(default)]
+        energy: Option<u64>,
+    },
+
+    /// Clear the drop queue of the factory at a cell.
+    ClearQueue { cell: u8 },
+}
+
+impl Game {
+    /// Edit the inventory of a user as admin.
+    ///
+    /// Returns the new inventory, or `None` if the edit is invalid.
+    pub fn admin_edit_inventory(
+        &self,
+        admin_id: u32,
+        user_id: u32,
+        config: &Config,
+        edit: &InventoryEdit,
+    ) -> Option<ClientInventory> {
+        // Target must be a game user
+        if !config.user_can(user_id, Permission::Play) {
+            return None;
+        }
+
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+        let users = self.users.read().unwrap();
+        let mut user = users.get(&user_id).unwrap().write().unwrap();
+        let inventory = &mut user.inventory;
+
+        match edit {
+            InventoryEdit::SetItem { cell, item } => {
+                if *cell as usize >= inventory.grid.items.len() {
+                    return None;
+                }
+                let item = match item {
+                    Some(item) => {
+                        let item = config.item(item)?.clone();
+                        inventory.discover_item(item.id.clone());
+                        Some(GameItem::from_config(self.tick(), item))
+                    }
+                    None => None,
+                };
+                inventory.grid.items[*cell as usize] = item;
+            }
+            InventoryEdit::SetBalance { money, energy } => {
+                inventory.money = money.unwrap_or(inventory.money);
+                inventory.energy = energy.unwrap_or(inventory.energy);
+            }
+            InventoryEdit::ClearQueue { cell } => {
+                inventory
+                    .grid
+                    .items
+                    .get_mut(*cell as usize)?
+                    .as_mut()?
+                    .clear_queue();
+            }
+        }
+
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::AdminEdit {
+                admin: admin_id,
+                edit: edit.clone(),
+            },
+        );
+
+        let inventory = ClientInventory::from_game(&user.inventory)
+            .expect("failed to transpose game to client inventory");
+        Some(inventory)
+    }
+}
@@ -0,0 +1,150 @@
+//! Admin announcements.
+//!
+//! Admins may announce free text messages to all users, a single user, or all admins. The
+//! latest announcements are kept, so clients connecting later still receive them.
+
+use serde::{Deserialize, Serialize};
+
+use super::journal::{JournalEventKind, JournalGuard};
+use super::Game;
+use crate::config::{Config, Permission};
+
+/// Maximum length of an announcement text, in characters.
+pub const ANNOUNCEMENT_MAX_LEN: usize = 500;
+
+/// Number of latest announcements to keep.
+const ANNOUNCEMENT_HISTORY_LIMIT: usize = 100;
+
+/// An admin announcement.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Announcement {
+    /// Announcement ID.
+    pub id: u32,
+
+    /// Game tick the announcement was made at.
+    pub tick: u64,
+
+    /// Time the announcement was made at, in seconds since the UNIX epoch.
+    pub time: u64,
+
+    /// Admin user that made the announcement.
+    pub from: u32,
+
+    /// Users to show the announcement to.
+    pub target: AnnouncementTarget,
+
+    /// Severity level.
+    pub severity: Severity,
+
+    /// Whether to show the announcement until dismissed.
+    pub persistent: bool,
+
+    /// Announcement text.
+    pub text: String,
+}
+
+/// Users to show an announcement to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum AnnouncementTarget {
+    /// All users.
+    All,
+
+    /// A single user.
+    User(u32),
+
+    /// All admin users.
+    Admins,
+}
+
+impl AnnouncementTarget {
+    /// Whether the given user is targeted.
+    pub fn includes(&self, config: &Config, user_id: u32) -> bool {
+        match self {
+            AnnouncementTarget::All => true,
+            AnnouncementTarget::User(id) => *id == user_id,
+            AnnouncementTarget::Admins => config.user_can(user_id, Permission::Admin),
+        }
+    }
+}
+
+/// Announcement severity level.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Game {
+    /// Make an announcement as admin.
+    ///
+    /// Returns the announcement, or `None` if the text or target is invalid.
+    pub fn announce(
+        &self,
+        config: &Config,
+        from: u32,
+        target: AnnouncementTarget,
+        severity: Severity,
+        persistent: bool,
+        text: &str,
+    ) -> Option<Announcement> {
+        // Validate text and target
+        let text = text.trim();
+        if text.is_empty() || text.chars().count() > ANNOUNCEMENT_MAX_LEN {
+            return None;
+        }
+        if let AnnouncementTarget::User(user_id) = target {
+            config.user(user_id)?;
+        }
+
+        let mut journal = self.journal.lock();
+        let id = self
+            .announcements
+            .read()
+            .unwrap()
+            .last()
+            .map(|a| a.id + 1)
+            .unwrap_or(1);
+        let announcement = Announcement {
+            id,
+            tick: self.tick(),
+            time: crate::auth::now(),
+            from,
+            target,
+            severity,
+            persistent,
+            text: text.into(),
+        };
+        self.add_announcement_journaled(&mut journal, announcement.clone());
+        Some(announcement)
+    }
+
+    /// Add an announcement to the history, and journal it.
+    pub(super) fn add_announcement_journaled(
+        &self,
+        journal: &mut JournalGuard,
+        announcement: Announcement,
+    ) {
+        let mut announcements = self.announcements.write().unwrap();
+        announcements.push(announcement.clone());
+        if announcements.len() > ANNOUNCEMENT_HISTORY_LIMIT {
+            let excess = announcements.len() - ANNOUNCEMENT_HISTORY_LIMIT;
+            announcements.drain(..excess);
+        }
+        journal.record(self.tick(), None, JournalEventKind::Announce(announcement));
+    }
+
+    /// Get the announcements shown to the given user, oldest first.
+    pub fn user_announcements(&self, config: &Config, user_id: u32) -> Vec<Announcement> {
+        self.announcements
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|a| a.target.includes(config, user_id))
+            .cloned()
+            .collect()
+    }
+}
@@ -1,66 +1,224 @@
+//! Outpost tokens.
+//!
+//! Tokens are signed with HMAC-SHA256 using the outposts secret. Each token holds the outpost ID,
+//! the rotation interval it was issued in and a random nonce. Tokens are valid for a configured
+//! time after their interval started, and may be redeemed once per user if single-use.
+//!
+//! Outposts may also enable static tokens, for printed codes. These only hold the outpost ID, and
+//! never expire.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::str;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Mutex;
 
-use sha1::{Digest, Sha1};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 
 use crate::config::Config;
 
-/// How often to change hash.
-const INTERVAL_SECS: u64 = 30;
+type HmacSha256 = Hmac<Sha256>;
+
+/// Interval marker of static tokens.
+const STATIC: &str = "static";
+
+/// A valid outpost token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutpostToken {
+    /// Outpost ID.
+    pub outpost: u32,
 
-/// Number of extra hashes valid before the current hash.
-const VALID_BEFORE: i64 = 2;
+    /// Random nonce, unique for each issued token, `None` if static.
+    pub nonce: Option<u64>,
+
+    /// Time the token expires at, in seconds since the UNIX epoch, `None` if static.
+    pub expires: Option<u64>,
+}
 
 /// Get current outpost token.
 pub fn get_outpost_token(config: &Config, id: u32) -> String {
-    let hash = get_hash_at(config, id, 0);
-    let token = format!("{}:{}", id, hash);
-    base64::encode(token)
+    get_outpost_token_at(config, id, crate::auth::now(), &mut rand::thread_rng())
+}
+
+/// Get outpost token at the given time in seconds since the UNIX epoch.
+pub fn get_outpost_token_at(config: &Config, id: u32, now: u64, rng: &mut impl Rng) -> String {
+    let msg = format!(
+        "{}:{}:{:x}",
+        id,
+        now / config.outposts.token_interval_secs,
+        rng.gen::<u64>()
+    );
+    signed(config, &msg)
+}
+
+/// Get static outpost token, that never expires.
+///
+/// Returns `None` if the outpost doesn't have static tokens enabled.
+pub fn get_static_outpost_token(config: &Config, id: u32) -> Option<String> {
+    if !config.outposts.outpost(id)?.static_code {
+        return None;
+    }
+    Some(signed(config, &format!("{}:{}", id, STATIC)))
 }
 
 /// Validate outpost token.
-pub fn validate_outpost_token(config: &Config, token: &str) -> Option<u32> {
+///
+/// Tokens for outposts that aren't configured are rejected.
+pub fn validate_outpost_token(config: &Config, token: &str) -> Option<OutpostToken> {
+    validate_outpost_token_at(config, token, crate::auth::now())
+}
+
+/// Validate outpost token at the given time in seconds since the UNIX epoch.
+pub fn validate_outpost_token_at(config: &Config, token: &str, now: u64) -> Option<OutpostToken> {
     let token = base64::decode(token).ok()?;
     let token = str::from_utf8(&token).ok()?;
 
-    let (id, hash) = token.split_once(":")?;
-    let id: u32 = id.parse().ok()?;
-
-    // Validate, including offset times before it
-    let valid = (-VALID_BEFORE..=0)
-        .rev()
-        .any(|offset| get_hash_at(config, id, offset) == hash);
-    if valid {
-        Some(id)
-    } else {
-        None
+    // Verify signature, in constant time
+    let (msg, mac) = token.rsplit_once(':')?;
+    sign(config, msg)
+        .verify_slice(&base64::decode(mac).ok()?)
+        .ok()?;
+
+    let mut parts = msg.split(':');
+    let id: u32 = parts.next()?.parse().ok()?;
+    let interval = parts.next()?;
+
+    // Static tokens must be enabled for the outpost
+    if interval == STATIC {
+        let outpost = config.outposts.outpost(id)?;
+        if parts.next().is_some() || !outpost.static_code {
+            return None;
+        }
+        return Some(OutpostToken {
+            outpost: id,
+            nonce: None,
+            expires: None,
+        });
+    }
+
+    let interval: u64 = interval.parse().ok()?;
+    let nonce = u64::from_str_radix(parts.next()?, 16).ok()?;
+    if parts.next().is_some() || !config.outposts.known(id) {
+        return None;
     }
+
+    // Token must be issued, and not be expired
+    let issued = interval.checked_mul(config.outposts.token_interval_secs)?;
+    let expires = issued.checked_add(config.outposts.token_valid_secs)?;
+    if now < issued || now >= expires {
+        return None;
+    }
+
+    Some(OutpostToken {
+        outpost: id,
+        nonce: Some(nonce),
+        expires: Some(expires),
+    })
 }
 
-/// Get timestamp, with number of offsets.
-fn time(offset: i64) -> i64 {
-    (SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        / INTERVAL_SECS) as i64
-        + offset
+/// Sign a message, and encode it with its signature as token.
+fn signed(config: &Config, msg: &str) -> String {
+    let mac = base64::encode(sign(config, msg).finalize().into_bytes());
+    base64::encode(format!("{}:{}", msg, mac))
 }
 
-/// Get the hash for an outpost at a given time.
-fn get_hash_at(config: &Config, id: u32, offset: i64) -> String {
-    hash(&format!(
-        "{}:{}:{}",
-        id,
-        time(offset),
-        &config.outposts.secret
-    ))
+/// Set up a MAC over a message with the outposts secret.
+fn sign(config: &Config, msg: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(config.outposts.secret.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(msg.as_bytes());
+    mac
+}
+
+/// Nonces of single-use tokens redeemed by users.
+///
+/// Persisted to file on each change, so tokens can't be redeemed again after a restart.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RedeemedTokens {
+    /// Expiry time of each redeemed token, by user ID and nonce.
+    tokens: Mutex<HashMap<u32, HashMap<u64, u64>>>,
+
+    /// File to persist redeemed tokens to, only kept in memory if `None`.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl RedeemedTokens {
+    /// Load redeemed tokens from file, and persist them to it.
+    pub fn load(path: PathBuf) -> Result<Self, ()> {
+        let tokens = crate::save::load(&path, |data| {
+            serde_json::from_slice::<Self>(data)
+                .map_err(|err| format!("couldn't deserialize redeemed tokens: {}", err))
+        })?;
+        Ok(Self {
+            path: Some(path),
+            ..tokens.unwrap_or_default()
+        })
+    }
+
+    /// Redeem a token for a user at the given time in seconds since the UNIX epoch.
+    ///
+    /// Returns `false` if the user already redeemed it. Expired tokens are forgotten. Static
+    /// tokens can always be redeemed.
+    pub fn redeem(&self, user_id: u32, token: &OutpostToken, now: u64) -> bool {
+        let (nonce, expires) = match (token.nonce, token.expires) {
+            (Some(nonce), Some(expires)) => (nonce, expires),
+            _ => return true,
+        };
+        let mut tokens = self.tokens.lock().unwrap();
+        for user in tokens.values_mut() {
+            user.retain(|_, expires| *expires > now);
+        }
+        tokens.retain(|_, user| !user.is_empty());
+        let redeemed = tokens
+            .entry(user_id)
+            .or_default()
+            .insert(nonce, expires)
+            .is_none();
+        if redeemed {
+            self.save(&tokens);
+        }
+        redeemed
+    }
+
+    /// Undo redeeming a token by a user, such as when the scan it was redeemed for failed.
+    pub fn release(&self, user_id: u32, token: &OutpostToken) {
+        let nonce = match token.nonce {
+            Some(nonce) => nonce,
+            None => return,
+        };
+        let mut tokens = self.tokens.lock().unwrap();
+        let removed = tokens
+            .get_mut(&user_id)
+            .and_then(|user| user.remove(&nonce))
+            .is_some();
+        if removed {
+            self.save(&tokens);
+        }
+    }
+
+    /// Save redeemed tokens to file, if persisted.
+    ///
+    /// Failing to save is logged but otherwise ignored, so scanning keeps working.
+    fn save(&self, tokens: &HashMap<u32, HashMap<u64, u64>>) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        // Serialize the locked tokens in the same format as this struct
+        let result = serde_json::to_vec(&serde_json::json!({ "tokens": tokens }))
+            .map_err(io::Error::from)
+            .and_then(|data| crate::save::write_atomic(path, &data));
+        if let Err(err) = result {
+            error!("Failed to save redeemed tokens: {}", err);
+        }
+    }
 }
 
-/// Hash a message, returns a string.
-fn hash(msg: &str) -> String {
-    let mut hasher = Sha1::new();
-    hasher.update(msg.as_bytes());
-    let result = hasher.finalize();
-    base64::encode(&result)
+/// Redeemed tokens file path for the given game save file path.
+pub fn path_for(game_path: &Path) -> PathBuf {
+    game_path.with_file_name(crate::REDEEMED_TOKENS_FILE)
 }
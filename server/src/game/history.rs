@@ -0,0 +1,102 @@
+//! Leaderboard history.
+//!
+//! Snapshots of each user's money, energy, score and key stats are taken at a configured tick
+//! interval, to show how the leaderboard developed over time.
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Game, GameUser};
+use crate::config::Config;
+
+/// Leaderboard state at a game tick.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistorySnapshot {
+    /// Game tick the snapshot was taken at.
+    pub tick: u64,
+
+    /// State of each user, ordered by user ID.
+    pub users: Vec<HistoryUser>,
+}
+
+/// User state in a leaderboard snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryUser {
+    /// User ID.
+    pub id: u32,
+
+    /// Account display name.
+    pub name: String,
+
+    /// User money.
+    pub money: u64,
+
+    /// User energy.
+    pub energy: u64,
+
+    /// User score.
+    pub score: f64,
+
+    /// Number of merges by user.
+    pub merge_count: u32,
+
+    /// Number of codes scanned by user.
+    pub code_count: u32,
+
+    /// Number of trades completed by user.
+    pub trade_count: u32,
+
+    /// Number of missions completed by user.
+    pub mission_count: u32,
+}
+
+impl HistoryUser {
+    fn from_game(config: &Config, user: &GameUser) -> Self {
+        let (score, _) = super::score::score(config, user);
+        Self {
+            id: user.id,
+            name: user
+                .config
+                .as_ref()
+                .map(|c| c.name.clone())
+                .unwrap_or_default(),
+            money: user.inventory.money,
+            energy: user.inventory.energy,
+            score,
+            merge_count: user.stats.merge_count.load(Ordering::Relaxed),
+            code_count: user.stats.code_count.load(Ordering::Relaxed),
+            trade_count: user.stats.trade_count.load(Ordering::Relaxed),
+            mission_count: user.stats.mission_count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Take a snapshot of the given users at the given tick.
+pub(super) fn snapshot(
+    config: &Config,
+    tick: u64,
+    users: &HashMap<u32, RwLock<GameUser>>,
+) -> HistorySnapshot {
+    let mut users: Vec<_> = users
+        .values()
+        .map(|user| HistoryUser::from_game(config, &user.read().unwrap()))
+        .collect();
+    users.sort_unstable_by_key(|user| user.id);
+    HistorySnapshot { tick, users }
+}
+
+impl Game {
+    /// Get leaderboard history snapshots taken after the given tick.
+    pub fn history(&self, since: Option<u64>) -> Vec<HistorySnapshot> {
+        self.history
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|snapshot| since.map(|since| snapshot.tick > since).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,369 @@
+//! Append-only game event journal.
+//!
+//! Every game state mutation is recorded as event in a JSON lines file, in the exact order it is
+//! applied. The journal can be replayed to rebuild the game state at any point.
+//!
+//! Game saves record the number of journaled events they include. After a crash, events
+//! journaled after the last save are replayed on top of the loaded save to continue where the
+//! game left off.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use super::admin::InventoryEdit;
+use super::announce::Announcement;
+use super::sanction::SanctionRecord;
+use super::Game;
+use crate::client::ClientActionTradeOffer;
+use crate::config::Config;
+use crate::types::{Amount, ItemRef};
+
+/// A journaled game event.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JournalEvent {
+    /// Game tick the event happened at.
+    pub tick: u64,
+
+    /// User the event is for, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<u32>,
+
+    /// Event kind and payload.
+    #[serde(flatten)]
+    pub kind: JournalEventKind,
+}
+
+/// Journaled game event kinds.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum JournalEventKind {
+    /// Game was reset, random source was seeded with seed.
+    Reset { seed: u64 },
+
+    /// Game was started or paused.
+    Running(bool),
+
+    /// Timed round started, or was paused or resumed for a pause window.
+    Round { started: bool, paused: bool },
+
+    /// Timed round finished, scoreboard was frozen.
+    Finish,
+
+    /// User joined the game, its initial state was created.
+    Join,
+
+    /// Admin made an announcement.
+    Announce(Announcement),
+
+    /// Admin applied a sanction or bonus to the user.
+    Sanction(SanctionRecord),
+
+    /// Admin edited the user inventory.
+    AdminEdit { admin: u32, edit: InventoryEdit },
+
+    /// Admin enabled or disabled an outpost.
+    OutpostEnabled {
+        admin: u32,
+        outpost: u32,
+        enabled: bool,
+    },
+
+    /// Game tick was processed, with items dropped by factories.
+    Tick { drops: Vec<JournalDrop> },
+
+    /// User swapped two cells.
+    Swap { cell: u8, other: u8 },
+
+    /// User merged two cells.
+    Merge { cell: u8, other: u8 },
+
+    /// User paid amounts.
+    Pay { amounts: Vec<Amount> },
+
+    /// User bought an item into a cell.
+    Buy { cell: u8, item: ItemRef },
+
+    /// User sold the item in a cell.
+    Sell { cell: u8 },
+
+    /// User scanned an outpost code, a random outpost if mocked, capturing it if capturable.
+    ScanCode { outpost: Option<u32> },
+
+    /// User submitted a mission.
+    SubmitMission { mission: u32 },
+
+    /// User offered a trade.
+    TradeOffer(ClientActionTradeOffer),
+
+    /// User accepted a trade.
+    TradeAccept { trade: u32 },
+
+    /// User declined or withdrew a trade.
+    TradeDecline { trade: u32 },
+}
+
+/// An item dropped by a factory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalDrop {
+    pub user: u32,
+    pub cell: u8,
+    pub item: ItemRef,
+}
+
+/// Game event journal.
+///
+/// Journal access is exclusive, and must be acquired before any other game lock for the whole
+/// duration of a mutation. This ensures events are journaled in the order they're applied.
+#[derive(Default)]
+pub struct Journal {
+    file: Mutex<Option<File>>,
+
+    /// Number of events in the journal.
+    events: AtomicU64,
+}
+
+impl Journal {
+    /// Open journal file to append to, creating it if it doesn't exist.
+    ///
+    /// A partially written last event, such as after a crash, is truncated.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+
+        // Truncate after last complete event, count events
+        let end = data
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        if end < data.len() {
+            warn!("Truncating partial last event in game journal");
+            file.set_len(end as u64)?;
+        }
+        let events = data[..end].iter().filter(|b| **b == b'\n').count() as u64;
+
+        Ok(Self {
+            file: Mutex::new(Some(file)),
+            events: AtomicU64::new(events),
+        })
+    }
+
+    /// Acquire exclusive journal access, to apply and record a mutation.
+    pub fn lock(&self) -> JournalGuard<'_> {
+        JournalGuard {
+            file: self.file.lock().unwrap(),
+            events: &self.events,
+        }
+    }
+}
+
+/// Exclusive journal access.
+pub struct JournalGuard<'a> {
+    file: MutexGuard<'a, Option<File>>,
+    events: &'a AtomicU64,
+}
+
+impl JournalGuard<'_> {
+    /// Number of events in the journal.
+    pub fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Record an event.
+    ///
+    /// Failing to write is logged but otherwise ignored, so the game keeps running.
+    pub fn record(&mut self, tick: u64, user: Option<u32>, kind: JournalEventKind) {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return,
+        };
+
+        let event = JournalEvent { tick, user, kind };
+        let result = serde_json::to_vec(&event)
+            .map_err(io::Error::from)
+            .and_then(|mut data| {
+                data.push(b'\n');
+                file.write_all(&data)
+            });
+        match result {
+            Ok(()) => {
+                self.events.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => error!("Failed to write event to game journal: {}", err),
+        }
+    }
+}
+
+/// Read all events from a journal file.
+///
+/// A partially written last event, such as after a crash, is skipped.
+pub fn read(path: &Path) -> io::Result<Vec<JournalEvent>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines().enumerate().peekable();
+    let mut events = vec![];
+    while let Some((i, line)) = lines.next() {
+        match serde_json::from_str(&line?) {
+            Ok(event) => events.push(event),
+            Err(err) if lines.peek().is_none() => {
+                warn!("Skipping partial last event in game journal: {}", err);
+            }
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid event on line {}: {}", i + 1, err),
+                ));
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Remove a journal file, succeeds if it doesn't exist.
+pub fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Journal file path for the given game save file path.
+pub fn path_for(game_path: &Path) -> PathBuf {
+    game_path.with_file_name(crate::GAME_JOURNAL_FILE)
+}
+
+impl Game {
+    /// Rebuild game state by replaying journaled events.
+    ///
+    /// Stops after the given tick if set. Mismatches with the journal, such as actions that
+    /// fail or factories dropping different items, are reported as warning.
+    pub fn replay(config: &Config, events: &[JournalEvent], until_tick: Option<u64>) -> Game {
+        let game = Game::default();
+
+        for event in events {
+            if until_tick.map(|until| event.tick > until).unwrap_or(false) {
+                break;
+            }
+
+            if !game.apply(config, event) {
+                warn!(
+                    "Replayed event did not apply cleanly at tick {}: {:?}",
+                    event.tick, event
+                );
+            }
+        }
+
+        game
+    }
+
+    /// Catch up with the journal, by replaying events journaled after this state was saved.
+    ///
+    /// Must be called before setting the journal, replayed events are not journaled again.
+    /// Returns the number of replayed events.
+    pub fn catch_up(&self, config: &Config, events: &[JournalEvent]) -> usize {
+        let saved = self.journaled.load(Ordering::Relaxed) as usize;
+        if saved > events.len() {
+            warn!(
+                "Game save includes {} journaled events, but journal has only {}, not catching up",
+                saved,
+                events.len()
+            );
+            return 0;
+        }
+
+        for event in &events[saved..] {
+            if !self.apply(config, event) {
+                warn!(
+                    "Caught up event did not apply cleanly at tick {}: {:?}",
+                    event.tick, event
+                );
+            }
+        }
+        self.journaled.store(events.len() as u64, Ordering::Relaxed);
+        events.len() - saved
+    }
+
+    /// Apply a journaled event to the game.
+    ///
+    /// Returns `false` if the event didn't apply like it did originally.
+    fn apply(&self, config: &Config, event: &JournalEvent) -> bool {
+        use JournalEventKind::*;
+
+        let user_id = event.user.unwrap_or(0);
+        match &event.kind {
+            Reset { seed } => {
+                self.reset_seeded(*seed);
+                true
+            }
+            Join => {
+                self.ensure_user(config, user_id);
+                true
+            }
+            Running(running) => {
+                self.set_running(*running);
+                true
+            }
+            Round { started, paused } => {
+                self.set_round_journaled(&mut self.journal.lock(), *started, *paused);
+                true
+            }
+            Finish => {
+                self.finish_round_journaled(&mut self.journal.lock(), config);
+                true
+            }
+            Tick { drops } => {
+                let (_, replayed) = self.tick_step(config);
+                self.tick() == event.tick && &replayed == drops
+            }
+            Swap { cell, other } => self.user_swap(user_id, config, *cell, *other).is_some(),
+            Merge { cell, other } => self.user_merge(user_id, config, *cell, *other).is_some(),
+            Pay { amounts } => self.user_pay(user_id, config, amounts).is_ok(),
+            Buy { cell, item } => match config.item(item) {
+                Some(item) => self
+                    .user_buy(user_id, config, *cell, item.clone())
+                    .is_some(),
+                None => false,
+            },
+            Sell { cell } => self.user_sell(user_id, config, *cell).is_some(),
+            ScanCode { outpost } => self.user_scan_code(user_id, config, *outpost).is_some(),
+            SubmitMission { mission } => self
+                .user_submit_mission(user_id, config, *mission)
+                .is_some(),
+            TradeOffer(offer) => self.user_trade_offer(user_id, config, offer).is_some(),
+            TradeAccept { trade } => self.user_trade_accept(user_id, config, *trade).is_some(),
+            TradeDecline { trade } => self.user_trade_decline(user_id, config, *trade).is_some(),
+            Announce(announcement) => {
+                self.add_announcement_journaled(&mut self.journal.lock(), announcement.clone());
+                true
+            }
+            Sanction(record) => self
+                .admin_sanction(
+                    record.admin,
+                    user_id,
+                    config,
+                    &record.sanction,
+                    &record.reason,
+                )
+                .is_some(),
+            AdminEdit { admin, edit } => self
+                .admin_edit_inventory(*admin, user_id, config, edit)
+                .is_some(),
+            OutpostEnabled {
+                admin,
+                outpost,
+                enabled,
+            } => self
+                .admin_set_outpost_enabled(*admin, config, *outpost, *enabled)
+                .is_some(),
+        }
+    }
+}
@@ -1,22 +1,42 @@
+pub mod admin;
+pub mod announce;
 pub mod code;
+pub mod history;
+pub mod journal;
+pub mod outpost;
+pub mod round;
+pub mod sanction;
+pub mod score;
+pub mod trade;
 pub mod types;
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Mutex, RwLock};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock, RwLockWriteGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use tokio::time::{self, Duration};
 
-use crate::client::{ClientInventory, ClientLeaderboardUser, ClientUserStats, MsgSendKind};
-use crate::config::{Config, ConfigItem};
+use crate::client::{
+    ClientActionTradeOffer, ClientInventory, ClientLeaderboardUser, ClientMission, ClientTrade,
+    ClientUserStats, MsgSendKind,
+};
+use crate::config::{Config, ConfigItem, Permission};
 use crate::state::SharedState;
 use crate::types::Amount;
 use crate::util::{i_to_xy, xy_to_i};
 use crate::ws;
+use announce::Announcement;
+use history::HistorySnapshot;
+use journal::{Journal, JournalDrop, JournalEventKind, JournalGuard};
+use outpost::{OutpostCapture, OutpostOwner};
+use round::GameRound;
+use trade::{GameTrade, GameTradeItem, TRADE_PENDING_LIMIT};
 pub use types::*;
 
 /// Threshold in number of changed items after which we should send the full inventory state,
@@ -26,26 +46,58 @@ const INV_CHANGE_PARTIAL_THRESHOLD: usize = 16;
 /// Run game.
 pub(crate) async fn run(state: SharedState) {
     let game = game_loop(state.clone());
-    let save = save_loop(state);
-    futures::future::select(Box::pin(game), Box::pin(save)).await;
+    let save = save_loop(state.clone());
+    let spectate = ws::spectate::spectate_loop(state);
+    futures::future::select(
+        Box::pin(game),
+        futures::future::select(Box::pin(save), Box::pin(spectate)),
+    )
+    .await;
 }
 
 /// Game logic loop.
+///
+/// The game clock is derived from wall clock time. All ticks due since the last processed tick
+/// are processed, so a stalled runtime or server restart doesn't slow the game down.
 pub(crate) async fn game_loop(state: SharedState) {
-    let mut interval = time::interval(Duration::from_millis(state.config.game.tick_millis));
-
     loop {
-        // Wait for tick
-        interval.tick().await;
+        update_round(&state);
 
-        // Process ticks
-        // TODO: catch up to missed ticks here
+        // Process all due ticks, not beyond the end of the round
         if state.game.running() {
-            state.game.process_ticks(&state, 1);
+            let mut ticks = state.game.take_due_ticks(&state.config, now_millis());
+            if ticks > 1 {
+                debug!("Catching up {} missed game ticks", ticks - 1);
+            }
+            if let Some(left) = state.game.round_ticks_left(&state.config) {
+                ticks = ticks.min(left);
+            }
+            if ticks > 0 {
+                state.game.process_ticks(&state, ticks);
+                update_round(&state);
+            }
         }
+
+        // Wait for next tick
+        let wait = state.game.next_tick_in(&state.config, now_millis());
+        time::sleep(Duration::from_millis(wait)).await;
     }
 }
 
+/// Update the round phase, and notify all clients if it changed.
+fn update_round(state: &SharedState) {
+    let change = match state.game.update_round(&state.config, crate::auth::now()) {
+        Some(change) => change,
+        None => return,
+    };
+    info!("Game round phase changed: {:?}", change);
+
+    let msg = MsgSendKind::GameState(state.game.running());
+    ws::send_to_all(state, None, &msg.into());
+    ws::send_round(state, None);
+    ws::spectate::send_to_spectators(state, None);
+}
+
 /// Game autosave loop.
 pub(crate) async fn save_loop(state: SharedState) {
     let mut interval = time::interval(Duration::from_secs(crate::GAME_SAVE_INTERVAL_SEC));
@@ -54,16 +106,25 @@ pub(crate) async fn save_loop(state: SharedState) {
         // Wait for tick
         interval.tick().await;
 
-        // Save game state
-        if let Err(err) = state.game.save() {
+        // Save game state, keep backups of autosaves
+        if let Err(err) = state.game.save(&state.game_path) {
             error!("Failed to autosave game state");
+            continue;
+        }
+        if let Err(err) = crate::save::backup(&state.game_path, crate::GAME_SAVE_BACKUP_COUNT) {
+            error!("Failed to back up game state: {}", err);
         }
     }
 }
 
+/// Random source used for all game randomness.
+///
+/// Seedable and portable, so a game is reproducible given the same seed and actions.
+pub type GameRng = ChaCha8Rng;
+
 /// Represents runnable game state.
 // TODO: when loading (deserializing) game, make sure all config properties get attached!
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize)]
 pub struct Game {
     /// Whether the game is running.
     running: AtomicBool,
@@ -74,19 +135,110 @@ pub struct Game {
     /// User state.
     // TODO: use better structure here, add user getter
     pub users: RwLock<HashMap<u32, RwLock<GameUser>>>,
+
+    /// Pending trade offers between users.
+    ///
+    /// Must be locked before `users` when both are needed.
+    #[serde(default)]
+    trades: RwLock<Vec<GameTrade>>,
+
+    /// Last used trade ID.
+    #[serde(default)]
+    trade_id: AtomicU32,
+
+    /// Seed the game random source was seeded with on reset.
+    #[serde(default)]
+    seed: AtomicU64,
+
+    /// Wall clock time in milliseconds the last game tick was due, zero if unknown.
+    #[serde(default)]
+    tick_time: AtomicU64,
+
+    /// Timed round state.
+    #[serde(default)]
+    round: RwLock<GameRound>,
+
+    /// Leaderboard history snapshots.
+    #[serde(default)]
+    history: RwLock<Vec<HistorySnapshot>>,
+
+    /// Latest admin announcements.
+    #[serde(default)]
+    announcements: RwLock<Vec<Announcement>>,
+
+    /// Outposts enabled or disabled by admins, overriding the configured flag.
+    #[serde(default)]
+    outposts_enabled: RwLock<HashMap<u32, bool>>,
+
+    /// Owners of captured outposts, by outpost ID.
+    #[serde(default)]
+    outpost_owners: RwLock<HashMap<u32, OutpostOwner>>,
+
+    /// Number of journaled events included in this state, when it was last saved.
+    #[serde(default)]
+    journaled: AtomicU64,
+
+    /// Game random source, its state is saved to continue the same random sequence.
+    #[serde(default = "entropy_rng")]
+    rng: Mutex<GameRng>,
+
+    /// Event journal, records all mutations.
+    #[serde(skip)]
+    journal: Journal,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self {
+            running: Default::default(),
+            tick: Default::default(),
+            users: Default::default(),
+            trades: Default::default(),
+            trade_id: Default::default(),
+            seed: Default::default(),
+            tick_time: Default::default(),
+            round: Default::default(),
+            history: Default::default(),
+            announcements: Default::default(),
+            outposts_enabled: Default::default(),
+            outpost_owners: Default::default(),
+            journaled: Default::default(),
+            rng: entropy_rng(),
+            journal: Default::default(),
+        }
+    }
 }
 
 impl Game {
+    /// Set the journal to record events to.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = journal;
+    }
+
     /// Make sure a given user is loaded, load it otherwise.
     pub fn ensure_user(&self, config: &Config, user_id: u32) {
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+    }
+
+    /// Make sure a given user is loaded, load and journal it otherwise.
+    fn ensure_user_journaled(
+        &self,
+        journal: &mut JournalGuard,
+        rng: &mut GameRng,
+        config: &Config,
+        user_id: u32,
+    ) {
         if !self.users.read().unwrap().contains_key(&user_id) {
-            self.add_user(config, user_id);
+            self.add_user(config, user_id, rng);
+            journal.record(self.tick(), Some(user_id), JournalEventKind::Join);
         }
     }
 
     /// Add a new user.
-    fn add_user(&self, config: &Config, user_id: u32) {
-        let user = GameUser::new(self.tick(), config, user_id);
+    fn add_user(&self, config: &Config, user_id: u32, rng: &mut GameRng) {
+        let user = GameUser::new(self.tick(), config, user_id, rng);
         self.users
             .write()
             .unwrap()
@@ -99,22 +251,61 @@ impl Game {
     }
 
     /// Set whether the game is running.
+    ///
+    /// When starting, the game clock continues from now, paused time is not caught up.
     pub fn set_running(&self, running: bool) {
-        self.running.store(running, Ordering::Relaxed)
+        let mut journal = self.journal.lock();
+        self.set_running_journaled(&mut journal, running);
+    }
+
+    /// Set whether the game is running, and journal it.
+    fn set_running_journaled(&self, journal: &mut JournalGuard, running: bool) {
+        if running && !self.running() {
+            self.tick_time.store(now_millis(), Ordering::Relaxed);
+        }
+        self.running.store(running, Ordering::Relaxed);
+        journal.record(self.tick(), None, JournalEventKind::Running(running));
     }
 
     /// Reset the game.
-    pub fn reset(&self) {
-        // Grab users lock
+    ///
+    /// Seeds the game random source with the configured seed, or a random seed if not set.
+    pub fn reset(&self, config: &Config) {
+        let seed = config.game.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        self.reset_seeded(seed);
+    }
+
+    /// Reset the game, seeding the game random source with the given seed.
+    pub fn reset_seeded(&self, seed: u64) {
+        // Grab trades and users lock, in the same order as trade actions
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        let mut trades = self.trades.write().unwrap();
         let mut users = self.users.write().unwrap();
 
-        // Drop all user states
+        // Drop all user states, pending trades, round state, history, announcements, outpost
+        // overrides and owners
         users.clear();
-
-        // Reset game tick
+        trades.clear();
+        *self.round.write().unwrap() = GameRound::default();
+        self.history.write().unwrap().clear();
+        self.announcements.write().unwrap().clear();
+        self.outposts_enabled.write().unwrap().clear();
+        self.outpost_owners.write().unwrap().clear();
+
+        // Reset game tick and random source
         self.tick.store(0, Ordering::Relaxed);
+        self.seed.store(seed, Ordering::Relaxed);
+        *rng = GameRng::seed_from_u64(seed);
+        journal.record(0, None, JournalEventKind::Reset { seed });
 
         drop(users);
+        drop(trades);
+    }
+
+    /// Get the seed the game random source was seeded with on reset.
+    pub fn seed(&self) -> u64 {
+        self.seed.load(Ordering::Relaxed)
     }
 
     /// Get current game tick.
@@ -122,35 +313,197 @@ impl Game {
         self.tick.load(Ordering::Relaxed)
     }
 
+    /// Take the number of game ticks due at the given wall clock time.
+    ///
+    /// Marks the returned ticks as processed. At most `max_catch_up_ticks` ticks are returned,
+    /// ticks beyond that are skipped.
+    pub fn take_due_ticks(&self, config: &Config, now: u64) -> u64 {
+        let tick_millis = config.game.tick_millis;
+        let last = self.tick_time.load(Ordering::Relaxed);
+
+        // Start clock now if unknown or if the wall clock went back
+        if last == 0 || last > now {
+            self.tick_time.store(now, Ordering::Relaxed);
+            return 0;
+        }
+
+        let due = (now - last) / tick_millis;
+        let ticks = due.min(config.game.max_catch_up_ticks);
+        if ticks < due {
+            warn!(
+                "Game is {} ticks behind, skipping {} ticks beyond maximum catch up",
+                due,
+                due - ticks
+            );
+        }
+        self.tick_time
+            .store(last + due * tick_millis, Ordering::Relaxed);
+        ticks
+    }
+
+    /// Milliseconds until the next game tick is due, at the given wall clock time.
+    pub fn next_tick_in(&self, config: &Config, now: u64) -> u64 {
+        let tick_millis = config.game.tick_millis;
+        let next = self.tick_time.load(Ordering::Relaxed) + tick_millis;
+        next.saturating_sub(now).min(tick_millis)
+    }
+
     /// Process the game by the given amount of ticks.
     ///
     /// This should be invoked from a game loop.
-    /// Calls `update` on the full game state afterwards.
+    /// Calls `update` on the full game state for each tick, and broadcasts all changes to
+    /// clients afterwards.
     pub fn process_ticks(&self, state: &SharedState, ticks: u64) {
-        trace!("Processing game tick");
+        trace!("Processing {} game tick(s)", ticks);
+        let from = self.tick() + 1;
+
+        // Process each tick, merge updates of each user
+        let mut updates: HashMap<u32, UserTickUpdate> = HashMap::new();
+        for _ in 0..ticks {
+            let (tick_updates, _) = self.tick_step(&state.config);
+            for update in tick_updates {
+                match updates.get_mut(&update.user_id) {
+                    Some(merged) => {
+                        merged.changed.extend(update.changed);
+                        merged.discovered |= update.discovered;
+                        merged.balances |= update.balances;
+                    }
+                    None => {
+                        updates.insert(update.user_id, update);
+                    }
+                }
+            }
+        }
+
+        self.broadcast_tick(state, from, updates.into_values().collect());
+    }
 
-        // Increase tick by 1
+    /// Advance the game state by a single tick, without notifying clients.
+    ///
+    /// Returns the user updates to broadcast, and the items dropped by factories.
+    pub fn tick_step(&self, config: &Config) -> (Vec<UserTickUpdate>, Vec<JournalDrop>) {
+        // Journal is locked first, no other mutation may happen during the tick
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        let users = self.users.read().unwrap();
+
+        // Increase tick by 1, collect income of captured outposts
         let tick = self.tick.fetch_add(1, Ordering::Relaxed) + 1;
+        let income = self.outpost_income(config, tick);
+
+        // Update each user, in stable order to consume randomness deterministically
+        let mut ids: Vec<_> = users.keys().cloned().collect();
+        ids.sort_unstable();
+        let mut updates = vec![];
+        let mut drops = vec![];
+        for id in ids {
+            let mut user = users[&id].write().unwrap();
+            let user = &mut *user;
+
+            // Update timed sanctions, frozen users don't update
+            let mut balances = user.sanctions.update(tick, &mut user.inventory);
+            let (mut changed, mut discovered, drop_count) = if user.sanctions.frozen {
+                Default::default()
+            } else {
+                user.update(config, tick, &mut *rng)
+            };
+
+            // Cells that hold an item after updating received a drop
+            drops.extend(changed.iter().filter_map(|cell| {
+                let item = user.inventory.grid.items[*cell as usize].as_ref()?;
+                Some(JournalDrop {
+                    user: user.id,
+                    cell: *cell,
+                    item: item.id.clone(),
+                })
+            }));
 
-        // Update each user
-        for user in self.users.read().unwrap().values() {
-            let mut user = user.write().unwrap();
-            let (changed, discovered, drop_count) = user.update(&state.config, tick);
+            // Increase stats
+            user.stats.inc_drop(drop_count);
+
+            // Pay outpost income if there's space for it, frozen users don't earn
+            let outposts = income.get(&id).filter(|_| !user.sanctions.frozen);
+            for amounts in outposts.into_iter().flatten() {
+                let (cells, new) =
+                    match user.inventory.add_amounts(config, tick, amounts, &mut *rng) {
+                        Ok(result) => result,
+                        Err(_) => continue,
+                    };
+                changed.extend(cells);
+                discovered |= new;
+                balances |= amounts_money(amounts) > 0 || amounts_energy(amounts) > 0;
+                user.stats.inc_money_earned(amounts_money(amounts));
+                user.stats.inc_energy_earned(amounts_energy(amounts));
+            }
+
+            updates.push(UserTickUpdate {
+                user_id: user.id,
+                changed,
+                discovered,
+                balances,
+            });
+        }
+
+        // Snapshot leaderboard at history interval
+        let interval = config.game.history_interval_ticks;
+        if interval > 0 && tick.is_multiple_of(interval) {
+            let snapshot = history::snapshot(config, tick, &users);
+            self.history.write().unwrap().push(snapshot);
+        }
+
+        drops.sort_unstable_by_key(|drop| (drop.user, drop.cell));
+        journal.record(
+            tick,
+            None,
+            JournalEventKind::Tick {
+                drops: drops.clone(),
+            },
+        );
+
+        (updates, drops)
+    }
+
+    /// Broadcast user updates of processed ticks to clients, starting at tick `from`.
+    fn broadcast_tick(&self, state: &SharedState, from: u64, updates: Vec<UserTickUpdate>) {
+        let tick = self.tick();
+
+        // Missions become available or unavailable at any of these ticks
+        let missions_changed =
+            (from..=tick).any(|tick| state.config.missions.iter().any(|m| m.changes_at(tick)));
+
+        let users = self.users.read().unwrap();
+        for update in updates {
+            let user = match users.get(&update.user_id) {
+                Some(user) => user.read().unwrap(),
+                None => continue,
+            };
 
             // Broadcast cell changes
-            broadcast_user_cell_changes(state, &user, changed);
+            broadcast_user_cell_changes(state, &user, update.changed);
+
+            // Send balances if changed
+            if update.balances {
+                let msg = MsgSendKind::InventoryBalances {
+                    money: user.inventory.money,
+                    energy: user.inventory.energy,
+                };
+                ws::send_to_user(state, None, user.id, &msg.into());
+            }
 
             // Send new inventory state if user discovered new items
-            if discovered {
+            if update.discovered {
                 debug!("User discovered new drop, notifying client");
                 let inventory = ClientInventory::from_game(&user.inventory)
                     .expect("failed to transpose game to client inventory");
                 let msg = MsgSendKind::InventoryDiscovered(inventory.discovered);
-                ws::send_to_user(&state, None, user.id, &msg.into());
+                ws::send_to_user(state, None, user.id, &msg.into());
             }
 
-            // Increase stats
-            user.stats.inc_drop(drop_count);
+            // Send new mission list if availability changed
+            if missions_changed {
+                let msg = MsgSendKind::Missions(ClientMission::list(&state.config, tick, &user));
+                ws::send_to_user(state, None, user.id, &msg.into());
+            }
         }
     }
 
@@ -173,6 +526,18 @@ impl Game {
         Some(stats)
     }
 
+    /// Get the user client missions.
+    pub fn user_client_missions(
+        &self,
+        config: &Config,
+        user_id: u32,
+    ) -> Option<Vec<ClientMission>> {
+        self.ensure_user(config, user_id);
+        let users = self.users.read().unwrap();
+        let user = users.get(&user_id)?.read().unwrap();
+        Some(ClientMission::list(config, self.tick(), &user))
+    }
+
     /// Swap two items for a user.
     pub fn user_swap(
         &self,
@@ -181,9 +546,14 @@ impl Game {
         cell: u8,
         other: u8,
     ) -> Option<ClientInventory> {
-        self.ensure_user(config, user_id);
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
 
         // TODO: validate indices
 
@@ -194,6 +564,11 @@ impl Game {
 
         // Increase stats
         user.stats.inc_swap();
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::Swap { cell, other },
+        );
 
         let inventory = ClientInventory::from_game(&user.inventory)
             .expect("failed to transpose game to client inventory");
@@ -210,9 +585,14 @@ impl Game {
         cell: u8,
         other: u8,
     ) -> Option<(ClientInventory, bool)> {
-        self.ensure_user(config, user_id);
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
 
         // TODO: validate indices
 
@@ -241,6 +621,11 @@ impl Game {
 
         // Increase stats
         user.stats.inc_merge();
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::Merge { cell, other },
+        );
 
         let inventory = ClientInventory::from_game(&user.inventory)
             .expect("failed to transpose game to client inventory");
@@ -254,16 +639,29 @@ impl Game {
         config: &Config,
         amounts: &[Amount],
     ) -> Result<HashSet<u8>, ()> {
-        self.ensure_user(config, user_id);
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return Err(());
+        }
 
         // Increase stats
         user.stats.inc_money_spent(amounts_money(amounts));
         user.stats.inc_energy_spent(amounts_energy(amounts));
 
         // Remove inventory amounts
-        user.inventory.remove_amounts(amounts)
+        let changed = user.inventory.remove_amounts(amounts, &mut *rng)?;
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::Pay {
+                amounts: amounts.to_vec(),
+            },
+        );
+        Ok(changed)
     }
 
     /// Buy an item for a user.
@@ -276,26 +674,39 @@ impl Game {
         cell: u8,
         item: ConfigItem,
     ) -> Option<(ClientInventory, bool)> {
-        self.ensure_user(config, user_id);
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
 
         // TODO: validate indices
 
         let item_id = item.id.clone();
-        let mut cell = &mut user.inventory.grid.items[cell as usize];
+        let mut cell_item = &mut user.inventory.grid.items[cell as usize];
 
         // Cell must be empty, put game item in it
-        if cell.is_some() {
+        if cell_item.is_some() {
             return None;
         }
-        *cell = Some(GameItem::from_config(self.tick(), item));
+        *cell_item = Some(GameItem::from_config(self.tick(), item));
 
         // Check for new item discovery
-        let discovered = user.inventory.discover_item(item_id);
+        let discovered = user.inventory.discover_item(item_id.clone());
 
         // Increase stats
         user.stats.inc_buy();
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::Buy {
+                cell,
+                item: item_id,
+            },
+        );
 
         let inventory = ClientInventory::from_game(&user.inventory)
             .expect("failed to transpose game to client inventory");
@@ -304,17 +715,22 @@ impl Game {
 
     /// Sell an item for a user.
     pub fn user_sell(&self, user_id: u32, config: &Config, cell: u8) -> Option<ClientInventory> {
-        self.ensure_user(config, user_id);
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
 
         // TODO: validate indices
 
         // Get sell, must contain item
-        let mut cell = &mut user.inventory.grid.items[cell as usize];
+        let mut cell_item = &mut user.inventory.grid.items[cell as usize];
 
         // Clear cell, get sell amount to earn
-        let amount = match cell.take() {
+        let amount = match cell_item.take() {
             Some(item) => item.config.as_ref().unwrap().sell,
             None => return None,
         };
@@ -323,6 +739,7 @@ impl Game {
         // Increase stats
         user.stats.inc_sell();
         user.stats.inc_money_earned(amount);
+        journal.record(self.tick(), Some(user_id), JournalEventKind::Sell { cell });
 
         let inventory = ClientInventory::from_game(&user.inventory)
             .expect("failed to transpose game to client inventory");
@@ -330,106 +747,451 @@ impl Game {
     }
 
     /// Scan a code for a user.
+    ///
+    /// Mocks scanning a random enabled outpost if no outpost is given. Fails if the outpost is
+    /// unknown or disabled, if it is still cooling down for the user, or if the user doesn't have
+    /// enough inventory space for its rewards. Capturable outposts are captured for the user.
+    ///
+    /// Returns the updated inventory on success, `true` if a new item was discovered, and the
+    /// capture if the outpost changed owner.
     pub fn user_scan_code(
         &self,
         user_id: u32,
         config: &Config,
-        outpost_id: u32,
-    ) -> Option<ClientInventory> {
-        self.ensure_user(config, user_id);
+        outpost: Option<u32>,
+    ) -> Option<(ClientInventory, bool, Option<OutpostCapture>)> {
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
         let users = self.users.read().unwrap();
         let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
+
+        // Pick random outpost for mock scans, outpost must be known and enabled
+        let outpost_id = match outpost {
+            Some(outpost_id) => outpost_id,
+            None => self.mock_outpost(config, &mut *rng)?,
+        };
+        if !self.outpost_enabled(config, outpost_id) {
+            return None;
+        }
+        let tick = self.tick();
+        let outpost_config = config.outposts.outpost(outpost_id);
+        let cooldown = outpost_config.and_then(|o| o.cooldown_ticks(config.game.tick_millis));
+        let reward = outpost_config.map(|o| &o.reward[..]).unwrap_or(&[]);
+        let formula = reward.is_empty() || outpost_config.map(|o| o.bonus).unwrap_or(false);
+
+        // Outpost must not be cooling down, user must have space for rewards
+        let cooling = cooldown
+            .and_then(|cooldown| {
+                let last = user.outpost_scans.get(&outpost_id)?;
+                Some(tick < last + cooldown)
+            })
+            .unwrap_or(false);
+        if cooling || !user.inventory.can_add_amounts(config, reward, 0) {
+            return None;
+        }
 
-        // Register the outpost, invalid if None is returned
+        // Register the outpost, invalid if scanned last time without a cooldown
         let unique_count = match user.register_outpost(outpost_id) {
             Some(unique) => unique,
+            None if cooldown.is_some() => 1,
             None => return None,
         };
+        user.outpost_scans.insert(outpost_id, tick);
 
         // Calculate money and energy to earn
-        let config = &config.outposts;
-        let money = (config.money_multiplier * unique_count as u64)
-            .max(config.money_min)
-            .min(config.money_max);
-        let energy = (config.energy_multiplier * unique_count as u64)
-            .max(config.energy_min)
-            .min(config.energy_max);
-
-        // Add earned money and energy to inventory
+        let (money, energy) = if formula {
+            let config = &config.outposts;
+            let money = (config.money_multiplier * unique_count as u64)
+                .max(config.money_min)
+                .min(config.money_max);
+            let energy = (config.energy_multiplier * unique_count as u64)
+                .max(config.energy_min)
+                .min(config.energy_max);
+            (money, energy)
+        } else {
+            (0, 0)
+        };
+
+        // Add rewards, earned money and energy to inventory
+        let (_, discovered) = user
+            .inventory
+            .add_amounts(config, tick, reward, &mut *rng)
+            .ok()?;
         user.inventory.money += money;
         user.inventory.energy += energy;
 
+        // Capture outpost
+        let capture = self.capture_outpost(config, outpost_id, user_id, tick);
+
         // Increase stats
         user.stats.inc_scan_code();
-        user.stats.inc_money_earned(money);
-        user.stats.inc_energy_earned(energy);
+        user.stats.inc_money_earned(money + amounts_money(reward));
+        user.stats
+            .inc_energy_earned(energy + amounts_energy(reward));
+        journal.record(tick, Some(user_id), JournalEventKind::ScanCode { outpost });
 
         let inventory = ClientInventory::from_game(&user.inventory)
             .expect("failed to transpose game to client inventory");
-        Some(inventory)
+        Some((inventory, discovered, capture))
+    }
+
+    /// Submit a mission for a user.
+    ///
+    /// Consumes the required amounts and gives the mission rewards. Fails if the mission isn't
+    /// available, was already completed, or if the user doesn't have the required amounts or
+    /// enough inventory space for the rewards.
+    ///
+    /// Returns the updated inventory on success and `true` if a new item was discovered.
+    pub fn user_submit_mission(
+        &self,
+        user_id: u32,
+        config: &Config,
+        mission_id: u32,
+    ) -> Option<(ClientInventory, bool)> {
+        let mut journal = self.journal.lock();
+        let tick = self.tick();
+        let mission = config.mission(mission_id).filter(|m| m.active(tick))?;
+
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+        let users = self.users.read().unwrap();
+        let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
+
+        // Mission must not be completed, user must have requirements and space for rewards
+        let freed = mission.require.iter().map(Amount::cells).sum();
+        if user.missions.contains(&mission_id)
+            || !user.inventory.has_amounts(&mission.require)
+            || !user
+                .inventory
+                .can_add_amounts(config, &mission.reward, freed)
+        {
+            return None;
+        }
+
+        // Consume requirements, give rewards
+        user.inventory
+            .remove_amounts(&mission.require, &mut *rng)
+            .ok()?;
+        let (_, discovered) = user
+            .inventory
+            .add_amounts(config, tick, &mission.reward, &mut *rng)
+            .ok()?;
+        user.missions.insert(mission_id);
+
+        // Increase stats
+        user.stats.inc_mission();
+        user.stats.inc_money_spent(amounts_money(&mission.require));
+        user.stats
+            .inc_energy_spent(amounts_energy(&mission.require));
+        user.stats.inc_money_earned(amounts_money(&mission.reward));
+        user.stats
+            .inc_energy_earned(amounts_energy(&mission.reward));
+        journal.record(
+            tick,
+            Some(user_id),
+            JournalEventKind::SubmitMission {
+                mission: mission_id,
+            },
+        );
+
+        let inventory = ClientInventory::from_game(&user.inventory)
+            .expect("failed to transpose game to client inventory");
+        Some((inventory, discovered))
     }
 
-    /// Get leaderboard entries.
-    pub fn leaderboard(&self) -> Vec<ClientLeaderboardUser> {
+    /// Get the pending trades a user is involved in.
+    pub fn user_client_trades(&self, config: &Config, user_id: u32) -> Vec<ClientTrade> {
+        self.trades
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|trade| trade.involves(user_id))
+            .filter_map(|trade| ClientTrade::from_game(config, trade).ok())
+            .collect()
+    }
+
+    /// Offer a trade from a user to another user.
+    ///
+    /// The offered cells, money and energy are taken out of the user inventory and held in
+    /// escrow until the trade is accepted or declined.
+    ///
+    /// Returns the updated inventory and the new trade on success.
+    pub fn user_trade_offer(
+        &self,
+        user_id: u32,
+        config: &Config,
+        offer: &ClientActionTradeOffer,
+    ) -> Option<(ClientInventory, ClientTrade)> {
+        let mut journal = self.journal.lock();
+        // Receiving user must be another user that is playing the game
+        if offer.to == user_id || !config.user_can(offer.to, Permission::Play) {
+            return None;
+        }
+
+        // Offered cells must be valid and unique, something must be traded
+        let cells: HashSet<u8> = offer.cells.iter().cloned().collect();
+        if cells.len() != offer.cells.len() || cells.iter().any(|c| *c as u16 >= crate::INV_SIZE) {
+            return None;
+        }
+        let offers_any = !cells.is_empty() || offer.money > 0 || offer.energy > 0;
+        if !offers_any && offer.request.is_empty() {
+            return None;
+        }
+
+        // Requested items must exist
+        let request_valid = offer.request.iter().all(|amount| match amount {
+            Amount::Item { item, quantity } => *quantity > 0 && config.item(item).is_some(),
+            _ => true,
+        });
+        if !request_valid {
+            return None;
+        }
+
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+        self.ensure_user_journaled(&mut journal, &mut rng, config, offer.to);
+        let mut trades = self.trades.write().unwrap();
+        let users = self.users.read().unwrap();
+        let mut user = users.get(&user_id).unwrap().write().unwrap();
+        if user.sanctions.frozen {
+            return None;
+        }
+
+        // Limit number of pending offers
+        let pending = trades.iter().filter(|t| t.from == user_id).count();
+        if pending >= TRADE_PENDING_LIMIT {
+            return None;
+        }
+
+        // User must have offered resources
+        let has_cells = cells
+            .iter()
+            .all(|cell| user.inventory.grid.items[*cell as usize].is_some());
+        if !has_cells || user.inventory.money < offer.money || user.inventory.energy < offer.energy
+        {
+            return None;
+        }
+
+        // Take offered resources into escrow
+        user.inventory.money -= offer.money;
+        user.inventory.energy -= offer.energy;
+        let items = offer
+            .cells
+            .iter()
+            .map(|cell| GameTradeItem {
+                cell: *cell,
+                item: user.inventory.grid.items[*cell as usize].take().unwrap(),
+            })
+            .collect();
+
+        let trade = GameTrade {
+            id: self.trade_id.fetch_add(1, Ordering::Relaxed) + 1,
+            from: user_id,
+            to: offer.to,
+            items,
+            money: offer.money,
+            energy: offer.energy,
+            request: offer.request.clone(),
+            tick: self.tick(),
+        };
+        let client_trade = ClientTrade::from_game(config, &trade).ok()?;
+        trades.push(trade);
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::TradeOffer(offer.clone()),
+        );
+
+        let inventory = ClientInventory::from_game(&user.inventory)
+            .expect("failed to transpose game to client inventory");
+        Some((inventory, client_trade))
+    }
+
+    /// Accept a trade offered to a user.
+    ///
+    /// Atomically moves the requested amounts to the offering user, and everything held in escrow
+    /// to the accepting user. Fails if either user is frozen, if the accepting user doesn't have
+    /// the requested amounts, or if either user doesn't have enough free inventory space.
+    ///
+    /// Returns the ID of the offering user on success.
+    pub fn user_trade_accept(&self, user_id: u32, config: &Config, trade_id: u32) -> Option<u32> {
+        let mut journal = self.journal.lock();
+        let mut trades = self.trades.write().unwrap();
+        let index = trades
+            .iter()
+            .position(|t| t.id == trade_id && t.to == user_id)?;
+        let from_id = trades[index].from;
+
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+        self.ensure_user_journaled(&mut journal, &mut rng, config, from_id);
+        let users = self.users.read().unwrap();
+        let (mut from, mut to) = lock_user_pair(&users, from_id, user_id);
+        if from.sanctions.frozen || to.sanctions.frozen {
+            return None;
+        }
+
+        // Accepting user must have requested amounts, both users need space for received items
+        let trade = &trades[index];
+        if !to.inventory.has_amounts(&trade.request) {
+            return None;
+        }
+        let to_space = to.inventory.grid.count_free_cells() + trade.request_item_count();
+        let from_space = from.inventory.grid.count_free_cells();
+        if to_space < trade.items.len() || from_space < trade.request_item_count() {
+            return None;
+        }
+
+        // Move requested amounts to offering user
+        for amount in &trade.request {
+            match amount {
+                Amount::Money { money } => {
+                    to.inventory.money -= money;
+                    from.inventory.money += money;
+                }
+                Amount::Energy { energy } => {
+                    to.inventory.energy -= energy;
+                    from.inventory.energy += energy;
+                }
+                Amount::Item { item, quantity } => {
+                    for _ in 0..*quantity {
+                        // Availability is checked above, this should never fail
+                        let item = match to.inventory.grid.take_item(item, &mut *rng) {
+                            Some((_, item)) => item,
+                            None => {
+                                error!("Failed to take requested trade item, possible data race?");
+                                continue;
+                            }
+                        };
+                        from.inventory.discover_item(item.id.clone());
+                        let _ = from.inventory.grid.place_item(item, &mut *rng);
+                    }
+                }
+            }
+        }
+
+        // Move escrow to accepting user
+        let trade = trades.remove(index);
+        to.inventory.money += trade.money;
+        to.inventory.energy += trade.energy;
+        for GameTradeItem { item, .. } in trade.items {
+            to.inventory.discover_item(item.id.clone());
+            let _ = to.inventory.grid.place_item(item, &mut *rng);
+        }
+
+        // Increase stats
+        from.stats.inc_trade();
+        to.stats.inc_trade();
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::TradeAccept { trade: trade_id },
+        );
+
+        Some(from_id)
+    }
+
+    /// Decline a trade offered to a user, or withdraw a trade offered by a user.
+    ///
+    /// Everything held in escrow is returned to the offering user.
+    ///
+    /// Returns the IDs of the offering and receiving user on success.
+    pub fn user_trade_decline(
+        &self,
+        user_id: u32,
+        config: &Config,
+        trade_id: u32,
+    ) -> Option<(u32, u32)> {
+        let mut journal = self.journal.lock();
+        let mut trades = self.trades.write().unwrap();
+        let index = trades
+            .iter()
+            .position(|t| t.id == trade_id && t.involves(user_id))?;
+        let trade = trades.remove(index);
+        let (from_id, to_id) = (trade.from, trade.to);
+
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, from_id);
+        let users = self.users.read().unwrap();
+        let mut from = users.get(&from_id).unwrap().write().unwrap();
+        trade.refund(&mut from, &mut *rng);
+        journal.record(
+            self.tick(),
+            Some(user_id),
+            JournalEventKind::TradeDecline { trade: trade_id },
+        );
+
+        Some((from_id, to_id))
+    }
+
+    /// Get leaderboard entries, ranked by score.
+    pub fn leaderboard(&self, config: &Config) -> Vec<ClientLeaderboardUser> {
         let mut items: Vec<_> = self
             .users
             .read()
             .unwrap()
             .values()
-            .filter_map(|user| ClientLeaderboardUser::from_game(&user.read().unwrap()).ok())
+            .filter_map(|user| ClientLeaderboardUser::from_game(config, &user.read().unwrap()).ok())
             .collect();
-        items.sort_unstable_by_key(|u| u.money);
-        items.reverse();
+        items.sort_unstable_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.money.cmp(&a.money))
+                .then_with(|| a.name.cmp(&b.name))
+        });
         items
     }
 
     /// Load game state from file.
-    pub fn load(config: &Config) -> Result<Self, ()> {
-        // Load default if file doesn't exist
-        let path = PathBuf::from(crate::GAME_SAVE_PATH);
-        if !path.is_file() {
-            info!("No game state file, starting fresh");
-            return Ok(Self::default());
-        }
-
-        // Load data from file
+    pub fn load(config: &Config, path: &Path) -> Result<Self, ()> {
+        // Load from file, falling back to backups if it is corrupt
         info!("Loading game state from file");
-        trace!("Reading game state file...");
-        let data = fs::read(path).expect("failed to read game state file");
+        let game = crate::save::load(path, |data| {
+            trace!("Deserializing game state data...");
+            let mut game: Self = serde_json::from_slice(data)
+                .map_err(|err| format!("couldn't deserialize game state: {}", err))?;
+
+            // Prepare configuration in game items
+            debug!("Attaching game item configuration models...");
+            game.attach_config(config).map_err(|_| {
+                "failed to link configuration to game objects, config might have changed?"
+                    .to_string()
+            })?;
+            Ok(game)
+        })?;
 
-        // Deserialize
-        trace!("Deserializing game state data...");
-        let mut game: Self = match serde_json::from_slice(data.as_slice()) {
-            Ok(state) => state,
-            Err(err) => {
-                error!(
-                    "Failed to load game state from file, couldn't deserialize: {}",
-                    err
-                );
-                return Err(());
+        // Load default if file doesn't exist
+        match game {
+            Some(game) => Ok(game),
+            None => {
+                info!("No game state file, starting fresh");
+                Ok(Self::default())
             }
-        };
-
-        // Prepare configuration in game items
-        debug!("Attaching game item configuration models...");
-        if let Err(err) = game.attach_config(config) {
-            error!("Failed to link configuration to game objects, config might have changed?",);
-            return Err(());
         }
-        Ok(game)
     }
 
     /// Save game state to file.
-    pub fn save(&self) -> Result<(), ()> {
+    pub fn save(&self, path: &Path) -> Result<(), ()> {
         info!("Saving game state to file");
 
-        // Serialize state
+        // Serialize state, hold journal to not include partial mutations
         trace!("Serializing game state...");
+        let journal = self.journal.lock();
+        self.journaled.store(journal.events(), Ordering::Relaxed);
         let data = if cfg!(debug_assertions) {
             serde_json::to_vec_pretty(self)
         } else {
             serde_json::to_vec(self)
         };
+        drop(journal);
         let data = match data {
             Ok(data) => data,
             Err(err) => {
@@ -440,7 +1202,7 @@ impl Game {
 
         // Write to file
         trace!("Writing game state to file...");
-        match fs::write(crate::GAME_SAVE_PATH, data.as_slice()) {
+        match crate::save::write_atomic(path, data.as_slice()) {
             Ok(result) => Ok(result),
             Err(err) => {
                 error!("Failed to save game state to file: {}", err);
@@ -455,10 +1217,64 @@ impl Game {
             let mut user = user.write().unwrap();
             user.attach_config(config)?;
         }
+        for trade in self.trades.write().unwrap().iter_mut() {
+            trade.attach_config(config)?;
+        }
         Ok(())
     }
 }
 
+/// Current wall clock time in milliseconds since the UNIX epoch.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Construct a game random source seeded from system entropy.
+fn entropy_rng() -> Mutex<GameRng> {
+    Mutex::new(GameRng::from_entropy())
+}
+
+/// User changes of a processed game tick, to broadcast to clients.
+pub struct UserTickUpdate {
+    /// User ID.
+    pub user_id: u32,
+
+    /// Changed inventory cells.
+    pub changed: HashSet<u8>,
+
+    /// Whether a new item was discovered.
+    pub discovered: bool,
+
+    /// Whether inventory balances changed.
+    pub balances: bool,
+}
+
+/// Acquire write locks for two different users.
+///
+/// Locks are always acquired in order of user ID to prevent deadlocks.
+fn lock_user_pair<'a>(
+    users: &'a HashMap<u32, RwLock<GameUser>>,
+    a: u32,
+    b: u32,
+) -> (
+    RwLockWriteGuard<'a, GameUser>,
+    RwLockWriteGuard<'a, GameUser>,
+) {
+    assert_ne!(a, b, "cannot lock same user twice");
+    if a < b {
+        let a = users.get(&a).unwrap().write().unwrap();
+        let b = users.get(&b).unwrap().write().unwrap();
+        (a, b)
+    } else {
+        let b = users.get(&b).unwrap().write().unwrap();
+        let a = users.get(&a).unwrap().write().unwrap();
+        (a, b)
+    }
+}
+
 /// Broadcast cell changes to user.
 ///
 /// This does some smart checks to figure out the best way of sending these changes.
@@ -0,0 +1,212 @@
+//! Configured outposts.
+//!
+//! Each configured outpost has its own rewards, cooldown and opening hours. Admins may enable or
+//! disable outposts while playing, overriding the configured flag until the game is reset.
+//!
+//! Outposts may be captured by scanning them. The owner earns passive income each income
+//! interval, until another user steals the outpost after its steal cooldown passed.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::journal::JournalEventKind;
+use super::Game;
+use crate::client::{ClientOutpost, ClientOutpostOwner};
+use crate::config::Config;
+use crate::types::Amount;
+
+/// Number of anonymous outposts picked from for mock scans, if no outposts are configured.
+const MOCK_OUTPOST_COUNT: u32 = 10;
+
+/// Owner of a captured outpost.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct OutpostOwner {
+    /// Owning user ID.
+    pub user: u32,
+
+    /// Game tick the outpost was captured at.
+    pub since: u64,
+}
+
+/// An outpost that changed owner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutpostCapture {
+    /// Outpost ID.
+    pub outpost: u32,
+
+    /// Previous owning user ID, `None` if the outpost was unclaimed.
+    pub previous: Option<u32>,
+}
+
+impl Game {
+    /// Whether the given outpost is known and enabled.
+    pub fn outpost_enabled(&self, config: &Config, outpost_id: u32) -> bool {
+        if config.outposts.any() {
+            return true;
+        }
+        let outpost = match config.outposts.outpost(outpost_id) {
+            Some(outpost) => outpost,
+            None => return false,
+        };
+        self.outposts_enabled
+            .read()
+            .unwrap()
+            .get(&outpost_id)
+            .cloned()
+            .unwrap_or(outpost.enabled)
+    }
+
+    /// Pick a random outpost for a mock scan, from all enabled outposts.
+    ///
+    /// Returns `None` if no outpost is enabled.
+    pub(super) fn mock_outpost(&self, config: &Config, rng: &mut impl Rng) -> Option<u32> {
+        if config.outposts.any() {
+            return Some(rng.gen_range(1..=MOCK_OUTPOST_COUNT));
+        }
+        let enabled: Vec<u32> = config
+            .outposts
+            .list
+            .iter()
+            .map(|outpost| outpost.id)
+            .filter(|id| self.outpost_enabled(config, *id))
+            .collect();
+        if enabled.is_empty() {
+            return None;
+        }
+        Some(enabled[rng.gen_range(0..enabled.len())])
+    }
+
+    /// Enable or disable a configured outpost, as admin.
+    ///
+    /// Returns `None` if the outpost is unknown.
+    pub fn admin_set_outpost_enabled(
+        &self,
+        admin_id: u32,
+        config: &Config,
+        outpost_id: u32,
+        enabled: bool,
+    ) -> Option<()> {
+        config.outposts.outpost(outpost_id)?;
+
+        let mut journal = self.journal.lock();
+        self.outposts_enabled
+            .write()
+            .unwrap()
+            .insert(outpost_id, enabled);
+        journal.record(
+            self.tick(),
+            None,
+            JournalEventKind::OutpostEnabled {
+                admin: admin_id,
+                outpost: outpost_id,
+                enabled,
+            },
+        );
+        Some(())
+    }
+
+    /// List configured outposts, with their current state.
+    pub fn client_outposts(&self, config: &Config, now: u64) -> Vec<ClientOutpost> {
+        config
+            .outposts
+            .list
+            .iter()
+            .map(|outpost| {
+                ClientOutpost::from_config(
+                    outpost,
+                    self.outpost_enabled(config, outpost.id),
+                    outpost.open_at(now),
+                )
+            })
+            .collect()
+    }
+
+    /// Capture an outpost for a user at the given tick, following its contest rules.
+    ///
+    /// Unclaimed outposts are captured right away, outposts owned by another user are stolen
+    /// once the steal cooldown since their capture passed. Must be called while holding the
+    /// journal lock, the capture is replayed through the scan that caused it.
+    ///
+    /// Returns the capture if the outpost changed owner.
+    pub(super) fn capture_outpost(
+        &self,
+        config: &Config,
+        outpost_id: u32,
+        user_id: u32,
+        tick: u64,
+    ) -> Option<OutpostCapture> {
+        let outpost = config.outposts.outpost(outpost_id).filter(|o| o.capture)?;
+        let mut owners = self.outpost_owners.write().unwrap();
+
+        let previous = owners.get(&outpost_id).cloned();
+        if let Some(owner) = previous {
+            let cooldown = outpost.steal_cooldown_ticks(config.game.tick_millis);
+            if owner.user == user_id || tick < owner.since + cooldown {
+                return None;
+            }
+        }
+
+        owners.insert(
+            outpost_id,
+            OutpostOwner {
+                user: user_id,
+                since: tick,
+            },
+        );
+        Some(OutpostCapture {
+            outpost: outpost_id,
+            previous: previous.map(|owner| owner.user),
+        })
+    }
+
+    /// Get the owner of an outpost, `None` if unclaimed.
+    pub fn outpost_owner(&self, outpost_id: u32) -> Option<OutpostOwner> {
+        self.outpost_owners
+            .read()
+            .unwrap()
+            .get(&outpost_id)
+            .cloned()
+    }
+
+    /// Collect the income users earn at the given tick from their enabled captured outposts.
+    ///
+    /// Income of each user is ordered by outpost ID, to consume randomness deterministically.
+    pub(super) fn outpost_income<'a>(
+        &self,
+        config: &'a Config,
+        tick: u64,
+    ) -> HashMap<u32, Vec<&'a [Amount]>> {
+        let owners = self.outpost_owners.read().unwrap();
+        let mut outposts: Vec<_> = owners.iter().collect();
+        outposts.sort_unstable_by_key(|(id, _)| **id);
+
+        let mut income: HashMap<u32, Vec<&[Amount]>> = HashMap::new();
+        for (id, owner) in outposts {
+            let outpost = match config.outposts.outpost(*id) {
+                Some(outpost) => outpost,
+                None => continue,
+            };
+            if outpost.income.is_empty()
+                || !tick.is_multiple_of(outpost.income_interval_ticks)
+                || !self.outpost_enabled(config, *id)
+            {
+                continue;
+            }
+            income.entry(owner.user).or_default().push(&outpost.income);
+        }
+        income
+    }
+
+    /// List owners of captured outposts, ordered by outpost ID.
+    pub fn client_outpost_owners(&self, config: &Config) -> Vec<ClientOutpostOwner> {
+        let owners = self.outpost_owners.read().unwrap();
+        let mut owners: Vec<_> = owners
+            .iter()
+            .filter_map(|(id, owner)| ClientOutpostOwner::from_game(config, *id, owner))
+            .collect();
+        owners.sort_unstable_by_key(|owner| owner.outpost_id);
+        owners
+    }
+}
@@ -0,0 +1,193 @@
+//! Timed game rounds.
+//!
+//! A round may be scheduled to start at a given time, be paused during configured time windows,
+//! and end at a given tick. When it ends, the game stops and the scoreboard is frozen.
+
+use serde::{Deserialize, Serialize};
+
+use super::journal::{JournalEventKind, JournalGuard};
+use super::Game;
+use crate::client::{ClientLeaderboardUser, ClientRound};
+use crate::config::Config;
+
+/// Round state.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GameRound {
+    /// Whether the round has started.
+    pub started: bool,
+
+    /// Whether the game is paused because of a pause window.
+    pub paused: bool,
+
+    /// Whether the round has finished.
+    pub finished: bool,
+
+    /// Final scoreboard, frozen when the round finished.
+    pub scoreboard: Vec<ClientLeaderboardUser>,
+}
+
+/// Round phase.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundPhase {
+    /// Waiting for the scheduled start.
+    Waiting,
+
+    /// Game is running.
+    Running,
+
+    /// Game is paused.
+    Paused,
+
+    /// Round has finished.
+    Finished,
+}
+
+/// A change in round phase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundChange {
+    /// Round started at its scheduled time.
+    Started,
+
+    /// Game was paused for a pause window.
+    Paused,
+
+    /// Game was resumed after a pause window.
+    Resumed,
+
+    /// Round finished, scoreboard is frozen.
+    Finished,
+}
+
+impl Game {
+    /// Update the round phase for the given time, in seconds since the UNIX epoch.
+    ///
+    /// Starts, pauses, resumes or finishes the game according to the round configuration.
+    /// Returns the change if the phase changed.
+    pub fn update_round(&self, config: &Config, now: u64) -> Option<RoundChange> {
+        let mut journal = self.journal.lock();
+        let (started, paused, finished) = {
+            let round = self.round.read().unwrap();
+            (round.started, round.paused, round.finished)
+        };
+        if finished {
+            return None;
+        }
+
+        // Start at scheduled time, or when started manually
+        if !started {
+            let scheduled = config.round.start_time().map(|start| now >= start);
+            if !self.running() && scheduled != Some(true) {
+                return None;
+            }
+            self.set_round_journaled(&mut journal, true, false);
+            if !self.running() {
+                self.set_running_journaled(&mut journal, true);
+                return Some(RoundChange::Started);
+            }
+        }
+
+        // Finish at end tick
+        let end_tick = config.round.end_tick(config.game.tick_millis);
+        if end_tick.map(|end| self.tick() >= end).unwrap_or(false) {
+            self.finish_round_journaled(&mut journal, config);
+            return Some(RoundChange::Finished);
+        }
+
+        // Pause and resume for pause windows
+        let in_pause = config.round.paused_at(now);
+        if in_pause && !paused && self.running() {
+            self.set_round_journaled(&mut journal, true, true);
+            self.set_running_journaled(&mut journal, false);
+            return Some(RoundChange::Paused);
+        }
+        if !in_pause && paused {
+            self.set_round_journaled(&mut journal, true, false);
+            if !self.running() {
+                self.set_running_journaled(&mut journal, true);
+                return Some(RoundChange::Resumed);
+            }
+        }
+
+        None
+    }
+
+    /// Set whether the round has started and is paused, and journal it.
+    pub(super) fn set_round_journaled(
+        &self,
+        journal: &mut JournalGuard,
+        started: bool,
+        paused: bool,
+    ) {
+        let mut round = self.round.write().unwrap();
+        round.started = started;
+        round.paused = paused;
+        journal.record(
+            self.tick(),
+            None,
+            JournalEventKind::Round { started, paused },
+        );
+    }
+
+    /// Finish the round, stop the game and freeze the scoreboard.
+    pub(super) fn finish_round_journaled(&self, journal: &mut JournalGuard, config: &Config) {
+        if self.running() {
+            self.set_running_journaled(journal, false);
+        }
+        let scoreboard = self.leaderboard(config);
+        let mut round = self.round.write().unwrap();
+        round.started = true;
+        round.paused = false;
+        round.finished = true;
+        round.scoreboard = scoreboard;
+        journal.record(self.tick(), None, JournalEventKind::Finish);
+    }
+
+    /// Number of ticks left until the round ends, if it has an end.
+    pub fn round_ticks_left(&self, config: &Config) -> Option<u64> {
+        config
+            .round
+            .end_tick(config.game.tick_millis)
+            .map(|end| end.saturating_sub(self.tick()))
+    }
+
+    /// Whether the round has finished.
+    pub fn round_finished(&self) -> bool {
+        self.round.read().unwrap().finished
+    }
+
+    /// Get the frozen final scoreboard, if the round has finished.
+    pub fn final_scoreboard(&self) -> Option<Vec<ClientLeaderboardUser>> {
+        let round = self.round.read().unwrap();
+        if round.finished {
+            Some(round.scoreboard.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Get the client round state, for the given time in seconds since the UNIX epoch.
+    pub fn client_round(&self, config: &Config, now: u64) -> ClientRound {
+        let round = self.round.read().unwrap();
+        let start = config.round.start_time().filter(|_| !round.started);
+        let phase = if round.finished {
+            RoundPhase::Finished
+        } else if self.running() {
+            RoundPhase::Running
+        } else if start.is_some() {
+            RoundPhase::Waiting
+        } else {
+            RoundPhase::Paused
+        };
+
+        let tick_millis = config.game.tick_millis;
+        ClientRound {
+            phase,
+            starts_in: start.map(|start| start.saturating_sub(now)),
+            remaining: self
+                .round_ticks_left(config)
+                .filter(|_| !round.finished)
+                .map(|ticks| ticks * tick_millis / 1000),
+        }
+    }
+}
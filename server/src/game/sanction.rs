@@ -0,0 +1,183 @@
+//! Per-user admin sanctions and bonuses.
+//!
+//! Referees may freeze a single user, fine or drain it, or grant a bonus, without stopping the
+//! game for everybody. A frozen user can't act, and its factories don't drop items. Each
+//! sanction is applied with a reason, which is kept with the user.
+
+use serde::{Deserialize, Serialize};
+
+use super::journal::JournalEventKind;
+use super::{Game, GameInventory};
+use crate::config::{Config, Permission};
+
+/// Maximum length of a sanction reason, in characters.
+pub const REASON_MAX_LEN: usize = 200;
+
+/// An admin sanction or bonus for a user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Sanction {
+    /// Freeze the user, for the given number of ticks or until unfrozen.
+    Freeze {
+        #[serde(default)]
+        ticks: Option<u64>,
+    },
+
+    /// Unfreeze the user.
+    Unfreeze,
+
+    /// Take money from the user, at most what it has.
+    Fine { money: u64 },
+
+    /// Drain energy from the user each tick, for the given number of ticks.
+    Drain { energy: u64, ticks: u64 },
+
+    /// Grant money and energy to the user.
+    Bonus {
+        #[serde(default)]
+        money: u64,
+        #[serde(default)]
+        energy: u64,
+    },
+}
+
+/// A sanction applied to a user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SanctionRecord {
+    /// Game tick the sanction was applied at.
+    pub tick: u64,
+
+    /// Admin user that applied the sanction.
+    pub admin: u32,
+
+    /// The sanction.
+    pub sanction: Sanction,
+
+    /// Reason for the sanction.
+    pub reason: String,
+}
+
+/// Sanction state of a user.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GameUserSanctions {
+    /// Whether the user is frozen.
+    pub frozen: bool,
+
+    /// Tick to unfreeze the user at, if timed.
+    pub frozen_until: Option<u64>,
+
+    /// Energy drained each tick, and the tick to stop draining at.
+    pub drain: Option<(u64, u64)>,
+
+    /// Applied sanctions, oldest first.
+    pub records: Vec<SanctionRecord>,
+}
+
+impl GameUserSanctions {
+    /// Apply a sanction at the given tick.
+    fn apply(&mut self, tick: u64, sanction: &Sanction, inventory: &mut GameInventory) {
+        match *sanction {
+            Sanction::Freeze { ticks } => {
+                self.frozen = true;
+                self.frozen_until = ticks.map(|ticks| tick + ticks);
+            }
+            Sanction::Unfreeze => {
+                self.frozen = false;
+                self.frozen_until = None;
+            }
+            Sanction::Fine { money } => {
+                inventory.money = inventory.money.saturating_sub(money);
+            }
+            Sanction::Drain { energy, ticks } => {
+                self.drain = Some((energy, tick + ticks));
+            }
+            Sanction::Bonus { money, energy } => {
+                inventory.money += money;
+                inventory.energy += energy;
+            }
+        }
+    }
+
+    /// Update timed sanctions for the given tick.
+    ///
+    /// Returns `true` if inventory balances changed.
+    pub fn update(&mut self, tick: u64, inventory: &mut GameInventory) -> bool {
+        if self
+            .frozen_until
+            .map(|until| tick >= until)
+            .unwrap_or(false)
+        {
+            self.frozen = false;
+            self.frozen_until = None;
+        }
+
+        match self.drain {
+            Some((_, until)) if tick > until => {
+                self.drain = None;
+                false
+            }
+            Some((energy, _)) if inventory.energy > 0 && energy > 0 => {
+                inventory.energy = inventory.energy.saturating_sub(energy);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Game {
+    /// Apply a sanction or bonus to a user as admin.
+    ///
+    /// Returns the applied sanction record, or `None` if the user or reason is invalid.
+    pub fn admin_sanction(
+        &self,
+        admin_id: u32,
+        user_id: u32,
+        config: &Config,
+        sanction: &Sanction,
+        reason: &str,
+    ) -> Option<SanctionRecord> {
+        // Target must be a game user, reason is required
+        if !config.user_can(user_id, Permission::Play) {
+            return None;
+        }
+        let reason = reason.trim();
+        if reason.is_empty() || reason.chars().count() > REASON_MAX_LEN {
+            return None;
+        }
+
+        let mut journal = self.journal.lock();
+        let mut rng = self.rng.lock().unwrap();
+        self.ensure_user_journaled(&mut journal, &mut rng, config, user_id);
+        let users = self.users.read().unwrap();
+        let mut user = users.get(&user_id).unwrap().write().unwrap();
+        let user = &mut *user;
+
+        let tick = self.tick();
+        user.sanctions.apply(tick, sanction, &mut user.inventory);
+        let record = SanctionRecord {
+            tick,
+            admin: admin_id,
+            sanction: sanction.clone(),
+            reason: reason.into(),
+        };
+        user.sanctions.records.push(record.clone());
+        journal.record(
+            tick,
+            Some(user_id),
+            JournalEventKind::Sanction(record.clone()),
+        );
+
+        Some(record)
+    }
+
+    /// Check whether a user is frozen.
+    pub fn user_frozen(&self, user_id: u32) -> bool {
+        self.users
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .map(|user| user.read().unwrap().sanctions.frozen)
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,72 @@
+//! Leaderboard scoring.
+//!
+//! A user score is a weighted sum of user properties, such as money, the value of held items and
+//! stats. Weights are configured in the scoring configuration, by default only money counts.
+
+use serde::{Deserialize, Serialize};
+
+use super::GameUser;
+use crate::config::Config;
+
+/// Points scored for a single property.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScorePart {
+    /// Property name, such as `money` or a stat name.
+    pub name: String,
+
+    /// Property value.
+    pub value: u64,
+
+    /// Points scored, the weighted value.
+    pub points: f64,
+}
+
+/// Calculate the score of a user.
+///
+/// Returns the total score, and the points of each property with a non-zero weight.
+pub fn score(config: &Config, user: &GameUser) -> (f64, Vec<ScorePart>) {
+    let scoring = &config.scoring;
+    let inventory = &user.inventory;
+
+    // Sell value of all held items
+    let item_value = inventory
+        .grid
+        .items
+        .iter()
+        .flatten()
+        .filter_map(|item| config.item(&item.id))
+        .map(|item| item.sell)
+        .sum();
+
+    let mut parts: Vec<(String, u64, f64)> = vec![
+        ("money".into(), inventory.money, scoring.money),
+        ("energy".into(), inventory.energy, scoring.energy),
+        ("item_value".into(), item_value, scoring.item_value),
+        (
+            "discovered".into(),
+            inventory.discovered.len() as u64,
+            scoring.discovered,
+        ),
+    ];
+
+    // Stats, in stable order
+    let mut stats: Vec<_> = scoring.stats.iter().collect();
+    stats.sort_unstable_by_key(|(name, _)| name.as_str());
+    for (name, weight) in stats {
+        if let Some(value) = user.stats.get(name) {
+            parts.push((name.clone(), value, *weight));
+        }
+    }
+
+    let parts: Vec<ScorePart> = parts
+        .into_iter()
+        .filter(|(_, _, weight)| *weight != 0.0)
+        .map(|(name, value, weight)| ScorePart {
+            name,
+            value,
+            points: value as f64 * weight,
+        })
+        .collect();
+    let total = parts.iter().map(|part| part.points).sum();
+    (total, parts)
+}
@@ -0,0 +1,105 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::types::Amount;
+
+use super::types::{GameItem, GameUser};
+
+/// Maximum number of pending outgoing trade offers per user.
+pub const TRADE_PENDING_LIMIT: usize = 5;
+
+/// A pending trade offer between two users.
+///
+/// Offered items, money and energy are held in escrow: they're taken out of the offering user
+/// inventory when the offer is made, and are either moved to the receiving user or returned when
+/// the offer is closed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameTrade {
+    /// Unique trade ID.
+    pub id: u32,
+
+    /// User ID of the offering user.
+    pub from: u32,
+
+    /// User ID of the receiving user.
+    pub to: u32,
+
+    /// Items held in escrow.
+    pub items: Vec<GameTradeItem>,
+
+    /// Money held in escrow.
+    pub money: u64,
+
+    /// Energy held in escrow.
+    pub energy: u64,
+
+    /// Amounts requested from the receiving user in return.
+    pub request: Vec<Amount>,
+
+    /// Game tick the offer was made at.
+    pub tick: u64,
+}
+
+impl GameTrade {
+    /// Check whether the given user is part of this trade.
+    pub fn involves(&self, user_id: u32) -> bool {
+        self.from == user_id || self.to == user_id
+    }
+
+    /// Number of inventory cells the requested amounts occupy.
+    pub fn request_item_count(&self) -> usize {
+        self.request.iter().map(Amount::cells).sum()
+    }
+
+    /// Attach configuration to items held in escrow.
+    pub fn attach_config(&mut self, config: &Config) -> Result<(), ()> {
+        for item in self.items.iter_mut() {
+            item.item.attach_config(config)?;
+        }
+        Ok(())
+    }
+
+    /// Return everything held in escrow to the offering user.
+    ///
+    /// Items are put back in their original cell if it is still free, or in a random free cell
+    /// otherwise. If the inventory is full, the item is sold for its sell price instead.
+    ///
+    /// Returns the list of changed cells.
+    pub fn refund(self, user: &mut GameUser, rng: &mut impl Rng) -> Vec<u8> {
+        debug_assert_eq!(self.from, user.id, "refunding trade to wrong user");
+
+        user.inventory.money += self.money;
+        user.inventory.energy += self.energy;
+
+        let mut changed = vec![];
+        for GameTradeItem { cell, item } in self.items {
+            let grid = &mut user.inventory.grid;
+            if grid.items[cell as usize].is_none() {
+                grid.items[cell as usize] = Some(item);
+                changed.push(cell);
+                continue;
+            }
+
+            let sell = item.config.as_ref().map(|c| c.sell).unwrap_or(0);
+            match grid.place_item(item, rng) {
+                Some(cell) => changed.push(cell),
+                None => {
+                    warn!("No space to return traded item to user, selling it instead");
+                    user.inventory.money += sell;
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// An item held in escrow by a trade.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameTradeItem {
+    /// Inventory cell the item was taken from.
+    pub cell: u8,
+
+    /// The item.
+    pub item: GameItem,
+}
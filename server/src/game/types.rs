@@ -1,9 +1,10 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::sanction::GameUserSanctions;
 use crate::config::{Config, ConfigItem, ConfigUser};
 use crate::types::{Amount, ItemRef};
 use crate::util::{i_to_xy, xy_to_i};
@@ -30,18 +31,33 @@ pub struct GameUser {
     /// Last item is scanned most recently.
     #[serde(default)]
     pub outposts: VecDeque<u32>,
+
+    /// Game tick each outpost was last scanned at, for outpost cooldowns.
+    #[serde(default)]
+    pub outpost_scans: HashMap<u32, u64>,
+
+    /// IDs of completed missions.
+    #[serde(default)]
+    pub missions: HashSet<u32>,
+
+    /// Admin sanctions, such as a freeze or energy drain.
+    #[serde(default)]
+    pub sanctions: GameUserSanctions,
 }
 
 impl GameUser {
     /// Construct a new user.
-    pub fn new(tick: u64, config: &Config, id: u32) -> Self {
+    pub fn new(tick: u64, config: &Config, id: u32, rng: &mut impl Rng) -> Self {
         Self {
             id,
-            inventory: GameInventory::from_config(tick, config)
+            inventory: GameInventory::from_config(tick, config, rng)
                 .unwrap_or_else(GameInventory::default),
             config: config.user(id).cloned(),
             stats: GameUserStats::default(),
             outposts: VecDeque::new(),
+            outpost_scans: HashMap::new(),
+            missions: HashSet::new(),
+            sanctions: GameUserSanctions::default(),
         }
     }
 
@@ -62,8 +78,13 @@ impl GameUser {
     /// Update game user.
     ///
     /// Returns list of changed inventory cells and `true` if a new item is discovered.
-    pub fn update(&mut self, config: &Config, tick: u64) -> (HashSet<u8>, bool, u32) {
-        self.inventory.update(config, tick)
+    pub fn update(
+        &mut self,
+        config: &Config,
+        tick: u64,
+        rng: &mut impl Rng,
+    ) -> (HashSet<u8>, bool, u32) {
+        self.inventory.update(config, tick, rng)
     }
 
     /// Register a new outpost in the list.
@@ -127,7 +148,7 @@ impl GameItem {
     }
 
     /// Attach configuration to this item.
-    fn attach_config(&mut self, config: &Config) -> Result<(), ()> {
+    pub fn attach_config(&mut self, config: &Config) -> Result<(), ()> {
         match config.item(&self.id) {
             Some(config) => {
                 self.config = Some(config.clone());
@@ -143,7 +164,7 @@ impl GameItem {
     /// Update game item.
     ///
     /// Returns `true` if changed.
-    fn update(&mut self, config: &Config, tick: u64) -> bool {
+    fn update(&mut self, config: &Config, tick: u64, rng: &mut impl Rng) -> bool {
         // We must have reached tick, and have not reached the drop limit
         // TODO: queue must have space
         let reached_tick = self.tick.map(|t| t < tick).unwrap_or(false);
@@ -161,7 +182,7 @@ impl GameItem {
             .map(|t| tick + t);
 
         // Select config item to drop
-        let item = match self.config.as_ref().unwrap().random_drop() {
+        let item = match self.config.as_ref().unwrap().random_drop(rng) {
             Some(item_ref) => item_ref,
             None => return false,
         };
@@ -248,6 +269,11 @@ impl GameItem {
     fn pop_queue_drop(&mut self) -> Option<ItemRef> {
         self.queue.pop_front()
     }
+
+    /// Clear the drop queue.
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
 }
 
 /// An inventory.
@@ -264,11 +290,11 @@ impl GameInventory {
     /// Get a default inventory from configuration.
     ///
     /// Returns `None` on failure.
-    pub fn from_config(tick: u64, config: &Config) -> Option<Self> {
+    pub fn from_config(tick: u64, config: &Config, rng: &mut impl Rng) -> Option<Self> {
         Some(Self {
             money: config.defaults.money,
             energy: config.defaults.energy,
-            grid: GameInventoryGrid::from_config(tick, config)?,
+            grid: GameInventoryGrid::from_config(tick, config, rng)?,
             discovered: config.defaults.inventory.iter().cloned().collect(),
         })
     }
@@ -276,9 +302,14 @@ impl GameInventory {
     /// Update game inventory.
     ///
     /// Returns list of changed cells and `true` if a new item is discovered.
-    fn update(&mut self, config: &Config, tick: u64) -> (HashSet<u8>, bool, u32) {
+    fn update(
+        &mut self,
+        config: &Config,
+        tick: u64,
+        rng: &mut impl Rng,
+    ) -> (HashSet<u8>, bool, u32) {
         // Update grid, collect changed cells and discovered items
-        let (changed, discovered, drop_count) = self.grid.update(config, tick);
+        let (changed, discovered, drop_count) = self.grid.update(config, tick, rng);
 
         // Check wheher new items are discovered
         let discovered = self.discover_items(discovered);
@@ -325,7 +356,11 @@ impl GameInventory {
     /// Returns `Ok(map)` listing changed cells.
     /// Returns `Err(())` if the inventory doesn't have enough resources, in which case it isn't
     /// modified.
-    pub fn remove_amounts(&mut self, amounts: &[Amount]) -> Result<HashSet<u8>, ()> {
+    pub fn remove_amounts(
+        &mut self,
+        amounts: &[Amount],
+        rng: &mut impl Rng,
+    ) -> Result<HashSet<u8>, ()> {
         // User must have enough resources
         if !self.has_amounts(amounts) {
             return Err(());
@@ -342,7 +377,7 @@ impl GameInventory {
                 }
                 Amount::Item { item, quantity } => {
                     for _ in 0..*quantity {
-                        if let Some(cell) = self.grid.remove_item(item) {
+                        if let Some(cell) = self.grid.remove_item(item, rng) {
                             changed.insert(cell);
                         }
                     }
@@ -351,6 +386,63 @@ impl GameInventory {
             changed
         }))
     }
+
+    /// Check whether the given amounts can be added to the inventory.
+    ///
+    /// The inventory must have enough free cells for all items, taking the given number of cells
+    /// that will be freed into account. All items must exist in the configuration.
+    pub fn can_add_amounts(&self, config: &Config, amounts: &[Amount], freed: usize) -> bool {
+        let cells: usize = amounts.iter().map(Amount::cells).sum();
+        let items_exist = amounts.iter().all(|amount| match amount {
+            Amount::Item { item, .. } => config.item(item).is_some(),
+            _ => true,
+        });
+        items_exist && self.grid.count_free_cells() + freed >= cells
+    }
+
+    /// Add the given amounts to the inventory.
+    ///
+    /// Items are placed in random free cells.
+    ///
+    /// Returns `Ok((changed, discovered))` listing changed cells and `true` if a new item was
+    /// discovered.
+    /// Returns `Err(())` if the amounts cannot be added, in which case the inventory isn't
+    /// modified.
+    pub fn add_amounts(
+        &mut self,
+        config: &Config,
+        tick: u64,
+        amounts: &[Amount],
+        rng: &mut impl Rng,
+    ) -> Result<(HashSet<u8>, bool), ()> {
+        if !self.can_add_amounts(config, amounts, 0) {
+            return Err(());
+        }
+
+        let mut changed = HashSet::new();
+        let mut discovered = false;
+        for amount in amounts {
+            match amount {
+                Amount::Money { money } => {
+                    self.money += money;
+                }
+                Amount::Energy { energy } => {
+                    self.energy += energy;
+                }
+                Amount::Item { item, quantity } => {
+                    let item = config.item(item).unwrap();
+                    for _ in 0..*quantity {
+                        let game_item = GameItem::from_config(tick, item.clone());
+                        if let Some(cell) = self.grid.place_item(game_item, rng) {
+                            changed.insert(cell);
+                        }
+                    }
+                    discovered = self.discover_item(item.id.clone()) || discovered;
+                }
+            }
+        }
+        Ok((changed, discovered))
+    }
 }
 
 /// An inventory grid.
@@ -363,7 +455,7 @@ impl GameInventoryGrid {
     /// Get a default inventory from configuration.
     ///
     /// Returns `None` on failure.
-    pub fn from_config(tick: u64, config: &Config) -> Option<Self> {
+    pub fn from_config(tick: u64, config: &Config, rng: &mut impl Rng) -> Option<Self> {
         let refs = &config.defaults.inventory;
 
         // Get config items from refs
@@ -384,8 +476,7 @@ impl GameInventoryGrid {
         items.extend((0..crate::INV_SIZE as usize - items.len()).map(|_| None));
 
         // Shuffle items
-        let mut rng = rand::thread_rng();
-        items.shuffle(&mut rng);
+        items.shuffle(rng);
 
         Some(Self { items })
     }
@@ -419,8 +510,8 @@ impl GameInventoryGrid {
     ///
     /// Returns cell index, returns `None` if there was no space.
     #[must_use]
-    pub fn place_item(&mut self, item: GameItem) -> Option<u8> {
-        self.find_free_cell().map(|coord| {
+    pub fn place_item(&mut self, item: GameItem, rng: &mut impl Rng) -> Option<u8> {
+        self.find_free_cell(rng).map(|coord| {
             *self.get_at_mut(coord.0, coord.1) = Some(item);
             xy_to_i(coord.0, coord.1)
         })
@@ -439,10 +530,14 @@ impl GameInventoryGrid {
     /// Remove an item from the grid.
     ///
     /// Returns cell index of removed item on success, `None` on failure.
-    pub fn remove_item(&mut self, item: &ItemRef) -> Option<u8> {
-        // TODO: use shared random source
-        let mut rng = rand::thread_rng();
+    pub fn remove_item(&mut self, item: &ItemRef, rng: &mut impl Rng) -> Option<u8> {
+        self.take_item(item, rng).map(|(index, _)| index)
+    }
 
+    /// Take an item out of the grid.
+    ///
+    /// Returns cell index and the taken item on success, `None` on failure.
+    pub fn take_item(&mut self, item: &ItemRef, rng: &mut impl Rng) -> Option<(u8, GameItem)> {
         // Find random cell index that holds this item
         self.items
             .iter()
@@ -453,16 +548,13 @@ impl GameInventoryGrid {
             .filter(|(_, i)| matches!(i, Some(i) if &i.id == item))
             .map(|(i, _)| i)
             .next()
-            .and_then(|index| self.items[index].take().map(|_| index as u8))
+            .and_then(|index| self.items[index].take().map(|i| (index as u8, i)))
     }
 
     /// Find a random free cell in the inventory.
     ///
     /// Returns `None` if no cell is available.
-    pub fn find_free_cell(&self) -> Option<(u32, u32)> {
-        // TODO: use shared random source
-        let mut rng = rand::thread_rng();
-
+    pub fn find_free_cell(&self, rng: &mut impl Rng) -> Option<(u32, u32)> {
         // Walk through all items, find first empty cell from random position
         self.items
             .iter()
@@ -488,16 +580,21 @@ impl GameInventoryGrid {
     /// Update game inventory.
     ///
     /// Return list of changed cell indices.
-    pub fn update(&mut self, config: &Config, tick: u64) -> (HashSet<u8>, HashSet<ItemRef>, u32) {
+    pub fn update(
+        &mut self,
+        config: &Config,
+        tick: u64,
+        rng: &mut impl Rng,
+    ) -> (HashSet<u8>, HashSet<ItemRef>, u32) {
         // Update items, drop updated state
         for item in self.items.iter_mut() {
             if let Some(item) = item {
-                item.update(config, tick);
+                item.update(config, tick, rng);
             }
         }
 
         // Place queued factory items onto field
-        let (mut changed, discovered) = self.place_queue_items(config, tick);
+        let (mut changed, discovered) = self.place_queue_items(config, tick, rng);
         let drop_count = changed.len() as u32;
 
         // Remove items that reached their drop limit
@@ -507,7 +604,12 @@ impl GameInventoryGrid {
     }
 
     /// Place factory queue items if there is space.
-    fn place_queue_items(&mut self, config: &Config, tick: u64) -> (HashSet<u8>, HashSet<ItemRef>) {
+    fn place_queue_items(
+        &mut self,
+        config: &Config,
+        tick: u64,
+        rng: &mut impl Rng,
+    ) -> (HashSet<u8>, HashSet<ItemRef>) {
         // Get number of free grid cells, there must be space
         let max = self.count_free_cells();
         if max <= 0 {
@@ -551,7 +653,7 @@ impl GameInventoryGrid {
 
             // Transpose into game item, place it
             let game_item = GameItem::from_config(tick, item.clone());
-            match self.place_item(game_item) {
+            match self.place_item(game_item, rng) {
                 Some(index) => {
                     changed.insert(index);
                     discovered.insert(item.id.clone());
@@ -628,9 +730,55 @@ pub struct GameUserStats {
 
     /// Energy earned by user from scanning codes.
     pub energy_earned: AtomicU64,
+
+    /// Number of trades completed by user.
+    #[serde(default)]
+    pub trade_count: AtomicU32,
+
+    /// Number of missions completed by user.
+    #[serde(default)]
+    pub mission_count: AtomicU32,
 }
 
 impl GameUserStats {
+    /// Names of all stats.
+    pub const NAMES: &'static [&'static str] = &[
+        "merge_count",
+        "buy_count",
+        "sell_count",
+        "swap_count",
+        "code_count",
+        "drop_count",
+        "money_spent",
+        "money_earned",
+        "energy_spent",
+        "energy_earned",
+        "trade_count",
+        "mission_count",
+    ];
+
+    /// Get a stat value by name.
+    ///
+    /// Returns `None` if there is no stat with this name.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        let value = match name {
+            "merge_count" => self.merge_count.load(Ordering::Relaxed) as u64,
+            "buy_count" => self.buy_count.load(Ordering::Relaxed) as u64,
+            "sell_count" => self.sell_count.load(Ordering::Relaxed) as u64,
+            "swap_count" => self.swap_count.load(Ordering::Relaxed) as u64,
+            "code_count" => self.code_count.load(Ordering::Relaxed) as u64,
+            "drop_count" => self.drop_count.load(Ordering::Relaxed) as u64,
+            "money_spent" => self.money_spent.load(Ordering::Relaxed),
+            "money_earned" => self.money_earned.load(Ordering::Relaxed),
+            "energy_spent" => self.energy_spent.load(Ordering::Relaxed),
+            "energy_earned" => self.energy_earned.load(Ordering::Relaxed),
+            "trade_count" => self.trade_count.load(Ordering::Relaxed) as u64,
+            "mission_count" => self.mission_count.load(Ordering::Relaxed) as u64,
+            _ => return None,
+        };
+        Some(value)
+    }
+
     /// Increase merge counter by one.
     pub fn inc_merge(&self) {
         self.merge_count.fetch_add(1, Ordering::Relaxed);
@@ -680,4 +828,14 @@ impl GameUserStats {
     pub fn inc_energy_earned(&self, amount: u64) {
         self.energy_earned.fetch_add(amount, Ordering::Relaxed);
     }
+
+    /// Increase trade counter by one.
+    pub fn inc_trade(&self) {
+        self.trade_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Increase mission counter by one.
+    pub fn inc_mission(&self) {
+        self.mission_count.fetch_add(1, Ordering::Relaxed);
+    }
 }
@@ -1,14 +1,43 @@
 pub const USER_UNKNOWN: &str = "Unknown user";
 pub const USER_INCORRECT_PASS: &str = "Incorrect password";
+pub const GAME_UNKNOWN: &str = "Unknown game";
+pub const SPECTATE_DISABLED: &str = "Spectating is disabled for this game";
+pub const SESSION_INVALID: &str = "Invalid session";
+pub const USER_NOT_ADMIN: &str = "User is not an admin";
+pub const USER_NOT_ALLOWED: &str = "User is not allowed to do this";
 
 pub const INTERNAL_ERROR: &str = "Internal error";
 
 // TODO: must be removed before release
 pub const NOT_YET_IMPLEMENTED: &str = "Not yet implemented";
 
+pub const ROUND_FINISHED: &str = "Game round has finished, reset the game to play again.";
+
 pub const INSUFFICIENT_RESOURCES_TO_BUY: &str = "Insufficient resources to buy";
 
 pub const NO_CODE_FREE_ENERGY: &str = "Codes not yet implemented. *Poof* you got free energy!";
 
 pub const USER_REWARDS_GIVEN: &str = "User rewards given.";
-pub const SCANNED_SAME_POST_LAST_TIME: &str = "No rewards. User scanned same post as last time.";
+pub const GAME_NOT_RUNNING: &str = "Game isn't running, no rewards given.";
+pub const INVENTORY_EDITED: &str = "Inventory edited.";
+pub const USER_FROZEN: &str = "Your team is frozen by a referee, you can't play right now.";
+pub const SANCTION_APPLIED: &str = "Sanction applied.";
+pub const SANCTION_INVALID: &str = "Invalid sanction, a reason is required.";
+pub const ANNOUNCEMENT_INVALID: &str = "Announcement is empty, too long or has an unknown target.";
+pub const INVENTORY_EDIT_INVALID: &str = "Invalid inventory edit.";
+pub const SCANNED_SAME_POST_LAST_TIME: &str =
+    "No rewards. Outpost is disabled, cooling down or was scanned last time, or the inventory is full.";
+pub const OUTPOST_CLOSED: &str = "This outpost is closed right now.";
+pub const OUTPOST_UNKNOWN: &str = "Unknown outpost.";
+pub const OUTPOST_NOT_STATIC: &str = "Outpost has no static code.";
+pub const OUTPOST_UPDATED: &str = "Outpost updated.";
+pub const OUTPOST_CAPTURED: &str = "You captured this outpost!";
+pub const OUTPOST_LOST: &str = "Another team captured one of your outposts.";
+
+pub const TRADE_INVALID: &str = "Trade offer is invalid.";
+pub const TRADE_ACCEPT_FAILED: &str =
+    "Could not accept trade, insufficient resources or inventory space.";
+
+pub const MISSION_COMPLETED: &str = "Mission completed!";
+pub const MISSION_REQUIREMENTS_NOT_MET: &str =
+    "Could not complete mission, insufficient resources or inventory space.";
@@ -4,12 +4,17 @@
 #[macro_use]
 extern crate log;
 
+pub(crate) mod audit;
 pub(crate) mod auth;
+pub(crate) mod cli;
 pub(crate) mod client;
 pub(crate) mod config;
 pub(crate) mod game;
 pub(crate) mod lang;
+pub(crate) mod qr;
 pub(crate) mod routes;
+pub(crate) mod save;
+pub(crate) mod sim;
 pub(crate) mod state;
 #[cfg(test)]
 pub mod tests;
@@ -18,78 +23,144 @@ pub(crate) mod util;
 pub(crate) mod web;
 pub(crate) mod ws;
 
+use std::fs;
 use std::pin::Pin;
 
+use clap::Parser;
 use futures::future::Future;
 
-use state::{SharedState, State};
+use auth::SessionManager;
+use cli::{Cli, Cmd};
+use state::{Server, SharedServer, State};
 
-/// Web server host.
-pub const HOST: ([u8; 4], u16) = ([0, 0, 0, 0], 8000);
-
-/// Config path.
+/// Default config path.
 pub const CONFIG_PATH: &str = "./../config/config.toml";
 
+/// Sprites directory path.
+///
+/// Served relative to the working directory, validated relative to the configuration file.
+pub const SPRITES_PATH: &str = "../sprites";
+
 /// Inventory width/height.
 pub const INV_WIDTH: u16 = 8;
 
 /// Inventory slot count.
 pub const INV_SIZE: u16 = INV_WIDTH.pow(2);
 
-/// Sessions file path.
-pub const SESSIONS_SAVE_PATH: &str = "save.sessions.json";
+/// Sessions file name, in save directory.
+pub const SESSIONS_SAVE_FILE: &str = "save.sessions.json";
+
+/// Game file name, in save directory.
+pub const GAME_SAVE_FILE: &str = "save.game.json";
+
+/// Game event journal file name, in save directory.
+pub const GAME_JOURNAL_FILE: &str = "journal.game.jsonl";
+
+/// Admin audit log file name, in save directory.
+pub const AUDIT_LOG_FILE: &str = "audit.game.jsonl";
 
-/// Game file path.
-pub const GAME_SAVE_PATH: &str = "save.game.json";
+/// Redeemed single-use outpost tokens file name, in save directory.
+pub const REDEEMED_TOKENS_FILE: &str = "save.tokens.json";
 
 /// Game autosave interval.
 pub const GAME_SAVE_INTERVAL_SEC: u64 = 60;
 
+/// Number of game autosave backups to keep.
+pub const GAME_SAVE_BACKUP_COUNT: usize = 10;
+
+/// Session pruning and autosave interval.
+pub const SESSION_PRUNE_INTERVAL_SEC: u64 = 60;
+
+/// Number of sessions autosave backups to keep.
+pub const SESSIONS_SAVE_BACKUP_COUNT: usize = 10;
+
 /// Main entrypoint.
 fn main() {
     // Initialize logging
     dotenv::dotenv();
     pretty_env_logger::init();
 
-    let state = state();
+    let cli = Cli::parse();
+    match cli.cmd.as_ref().unwrap_or(&Cmd::Serve) {
+        Cmd::Serve => serve(&cli),
+        Cmd::CheckConfig => cli::check_config(&cli),
+        Cmd::ResetSave { sessions } => cli::reset_save(&cli, *sessions),
+        Cmd::DumpState => cli::dump_state(&cli),
+        Cmd::ExportStats => cli::export_stats(&cli),
+        Cmd::ExportAudit => cli::export_audit(&cli),
+        Cmd::OutpostSheet => cli::outpost_sheet(&cli),
+        Cmd::HashPassword { password } => cli::hash_password(password.as_deref()),
+        Cmd::Replay { until_tick, output } => cli::replay(&cli, *until_tick, output.as_deref()),
+        Cmd::Simulate { options, json } => cli::simulate(&cli, options, *json),
+    }
+}
+
+/// Run the game server.
+fn serve(cli: &Cli) {
+    let server = server(cli);
+    let addr = cli.addr();
 
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
         .block_on(async {
-            let server = crate::web::server(state.clone());
-            let game_loop = crate::game::run(state.clone());
-            let quit_signal = quit_signal();
-
             type FutureType = Pin<Box<dyn Future<Output = ()>>>;
-            let server: FutureType = Box::pin(server);
-            let game_loop: FutureType = Box::pin(game_loop);
-            let quit_signal: FutureType = Box::pin(quit_signal);
+            let mut futures: Vec<FutureType> = vec![
+                Box::pin(crate::web::server(server.clone(), addr)),
+                Box::pin(crate::auth::prune_loop(server.clone())),
+                Box::pin(quit_signal()),
+            ];
+            for state in &server.games {
+                futures.push(Box::pin(crate::game::run(state.clone())));
+            }
 
-            futures::future::select_all([server, game_loop, quit_signal]).await;
+            futures::future::select_all(futures).await;
 
-            // Save game state before we quit
-            if let Err(err) = state.game.save() {
-                error!("Failed to save game state before quitting, this will lead to data loss");
+            // Save sessions and game states before we quit
+            if let Err(err) = server.sessions.save() {
+                error!("Failed to save sessions before quitting: {}", err);
+            }
+            for state in &server.games {
+                if let Err(err) = state.game.save(&state.game_path) {
+                    error!(
+                        "Failed to save state of game '{}' before quitting, this will lead to data loss",
+                        state.id
+                    );
+                }
             }
         })
 }
 
-/// Load shared state.
-fn state() -> SharedState {
+/// Load shared server state, with all games.
+fn server(cli: &Cli) -> SharedServer {
     info!("Initializing global state...");
-    let config = config::load().expect("failed to load game config");
+    let sessions =
+        SessionManager::load(cli.sessions_save_path()).expect("failed to load session manager");
+
+    let games = cli
+        .registry_games()
+        .iter()
+        .map(|game| {
+            let config = cli.load_config(game);
+            let game_path = cli.game_save_path(game);
+            if let Some(dir) = game_path.parent() {
+                fs::create_dir_all(dir).expect("failed to create game save directory");
+            }
 
-    let start = config.game.start;
-    let mut state = State::new(config);
+            let start = config.game.start && config.round.start.is_none();
+            let state = State::new(game, config, game_path);
 
-    // Start new games if configured
-    if start && state.game.tick() == 0 {
-        state.game.set_running(true);
-    }
+            // Start new games if configured, unless a round start is scheduled
+            if start && state.game.tick() == 0 {
+                state.game.set_running(true);
+            }
+
+            state.shared()
+        })
+        .collect();
 
-    state.shared()
+    Server::new(sessions, games).shared()
 }
 
 /// Quit signal handler.
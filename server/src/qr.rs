@@ -0,0 +1,78 @@
+//! QR code rendering of outpost tokens.
+//!
+//! Codes are rendered as SVG or PNG image, and as printable sheet with the static code of each
+//! outpost on its own page.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+use crate::config::Config;
+use crate::game::code;
+
+/// Minimum QR code image size in pixels.
+const MIN_SIZE: u32 = 256;
+
+/// Render data as SVG QR code.
+pub fn svg(data: &str) -> Option<String> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    Some(
+        code.render::<svg::Color>()
+            .min_dimensions(MIN_SIZE, MIN_SIZE)
+            .build(),
+    )
+}
+
+/// Render data as PNG QR code.
+pub fn png(data: &str) -> Option<Vec<u8>> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    let image = code
+        .render::<Luma<u8>>()
+        .min_dimensions(MIN_SIZE, MIN_SIZE)
+        .build();
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png).ok()?;
+    Some(png.into_inner())
+}
+
+/// Render a printable HTML sheet, with one page for each outpost that has static codes.
+///
+/// Returns `None` if no outpost has static codes enabled.
+pub fn sheet(config: &Config) -> Option<String> {
+    let pages: Vec<String> = config
+        .outposts
+        .list
+        .iter()
+        .filter_map(|outpost| {
+            let token = code::get_static_outpost_token(config, outpost.id)?;
+            Some(format!(
+                "<section>\n<h1>{}</h1>\n<p>Outpost #{}</p>\n{}\n<p>{}</p>\n</section>\n",
+                html_escape(&outpost.name),
+                outpost.id,
+                svg(&token)?,
+                html_escape(outpost.hint.as_deref().unwrap_or("")),
+            ))
+        })
+        .collect();
+    if pages.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Outposts</title>\n\
+        <style>\nbody {{ font-family: sans-serif; text-align: center; }}\n\
+        section {{ page-break-after: always; padding-top: 2em; }}\n\
+        svg {{ width: 70%; height: auto; }}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        pages.concat()
+    ))
+}
+
+/// Escape text for HTML.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,22 @@
+use warp::http::header::CONTENT_TYPE;
+use warp::reply::{with_header, Reply};
+
+use crate::audit::AuditQuery;
+use crate::state::SharedServer;
+
+/// Export the audit log of a game as CSV.
+///
+/// Requires a session token of an admin user in the `Authorization: Bearer <token>` header.
+pub fn audit_csv(
+    query: AuditQuery,
+    authorization: Option<String>,
+    server: SharedServer,
+) -> Box<dyn Reply> {
+    let state = match super::admin_state(&server, authorization) {
+        Ok(state) => state,
+        Err(reply) => return reply,
+    };
+
+    let csv = crate::audit::to_csv(&state.audit.query(&query));
+    Box::new(with_header(csv, CONTENT_TYPE, "text/csv"))
+}
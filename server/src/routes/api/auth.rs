@@ -3,15 +3,27 @@ use warp::reply::{json, Json, Reply};
 
 use crate::auth::SessionToken;
 use crate::client::ClientSession;
-use crate::state::SharedState;
-
-/// Get list of users.
-pub fn users(state: SharedState) -> Json {
-    let mut users: Vec<UserData> = state.config.users.iter().map(|t| t.into()).collect();
+use crate::state::SharedServer;
+
+/// Get list of users in a game.
+///
+/// Lists no users if the game is unknown.
+pub fn users(query: UsersQuery, server: SharedServer) -> Json {
+    let mut users: Vec<UserData> = match server.game_or_single(query.game.as_deref()) {
+        Some(state) => state.config.users.iter().map(|t| t.into()).collect(),
+        None => vec![],
+    };
     users.sort_by(|a, b| a.name.cmp(&b.name));
     json(&users)
 }
 
+/// Users query.
+#[derive(Deserialize, Debug)]
+pub struct UsersQuery {
+    #[serde(default)]
+    game: Option<String>,
+}
+
 #[derive(Serialize, Debug)]
 pub struct UserData {
     id: u32,
@@ -35,7 +47,15 @@ pub struct LoginResult {
 }
 
 /// Login route.
-pub fn login(data: LoginData, state: SharedState) -> Box<dyn Reply> {
+pub fn login(data: LoginData, user_agent: Option<String>, server: SharedServer) -> Box<dyn Reply> {
+    // Find game
+    let state = match server.game_or_single(data.game.as_deref()) {
+        Some(state) => state,
+        None => {
+            return Box::new(crate::web::ApiError::from(crate::lang::GAME_UNKNOWN).to_reply());
+        }
+    };
+
     // Find user with ID
     let config_user = match state.config.users.iter().find(|t| t.id == data.user) {
         Some(user) => user,
@@ -45,12 +65,17 @@ pub fn login(data: LoginData, state: SharedState) -> Box<dyn Reply> {
     };
 
     // Validate password
-    if config_user.password != data.password {
+    if !config_user.verify_password(&data.password) {
         return Box::new(crate::web::ApiError::from(crate::lang::USER_INCORRECT_PASS).to_reply());
     }
 
     // Create session
-    let session = state.sessions.add(data.user);
+    let session = server.sessions.add(
+        &state.id,
+        data.user,
+        state.config.sessions.max_age(),
+        user_agent,
+    );
 
     // Construct client session object
     let client_session = match ClientSession::from_session(&state.config, &session) {
@@ -72,25 +97,27 @@ pub fn login(data: LoginData, state: SharedState) -> Box<dyn Reply> {
 }
 
 /// Logout route.
-pub fn logout(data: SessionToken, state: SharedState) -> impl Reply {
+pub fn logout(data: SessionToken, server: SharedServer) -> impl Reply {
     // TODO: we might want to check session token validity here
 
-    state.sessions.remove(&data.token);
+    server.sessions.remove(&data.token);
     json(&true)
 }
 
 /// Login data.
 #[derive(Deserialize, Debug)]
 pub struct LoginData {
+    #[serde(default)]
+    game: Option<String>,
     user: u32,
     #[serde(default)]
     password: String,
 }
 
 /// Session validation route.
-pub fn validate(data: SessionToken, state: SharedState) -> impl Reply {
+pub fn validate(data: SessionToken, server: SharedServer) -> impl Reply {
     // Get session, fail if unavailable
-    let session = match state.sessions.get_valid(&data.token) {
+    let session = match server.sessions.get(&data.token) {
         Some(session) => session,
         None => {
             warn!(
@@ -101,6 +128,20 @@ pub fn validate(data: SessionToken, state: SharedState) -> impl Reply {
         }
     };
 
+    server.sessions.touch(&data.token);
+
+    // Find session game, game may have been removed from registry
+    let state = match server.game(&session.game) {
+        Some(state) => state,
+        None => {
+            warn!(
+                "Client tried to validate session for unknown game: {}",
+                session.game,
+            );
+            return json(&());
+        }
+    };
+
     // Construct client session object
     let session = match ClientSession::from_session(&state.config, &session) {
         Some(session) => session,
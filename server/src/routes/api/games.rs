@@ -0,0 +1,23 @@
+use serde::Serialize;
+use warp::reply::{json, Json};
+
+use crate::state::SharedServer;
+
+/// Get list of hosted games.
+pub fn games(server: SharedServer) -> Json {
+    let games: Vec<GameData> = server
+        .games
+        .iter()
+        .map(|game| GameData {
+            id: game.id.clone(),
+            name: game.name.clone(),
+        })
+        .collect();
+    json(&games)
+}
+
+#[derive(Serialize, Debug)]
+pub struct GameData {
+    id: String,
+    name: String,
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+use warp::reply::{json, Reply};
+
+use crate::state::SharedServer;
+
+/// Get leaderboard history of a game.
+///
+/// Requires a session token of an admin user in the `Authorization: Bearer <token>` header.
+pub fn history(
+    query: HistoryQuery,
+    authorization: Option<String>,
+    server: SharedServer,
+) -> Box<dyn Reply> {
+    let state = match super::admin_state(&server, authorization) {
+        Ok(state) => state,
+        Err(reply) => return reply,
+    };
+
+    Box::new(json(&state.game.history(query.since)))
+}
+
+/// History query.
+#[derive(Deserialize, Debug)]
+pub struct HistoryQuery {
+    /// Optional: only list snapshots taken after this tick.
+    #[serde(default)]
+    since: Option<u64>,
+}
@@ -1 +1,65 @@
+pub mod audit;
 pub mod auth;
+pub mod games;
+pub mod history;
+pub mod outposts;
+
+use warp::Reply;
+
+use crate::config::Permission;
+use crate::state::{SharedServer, SharedState};
+
+/// Get the game of an admin session, by the `Authorization: Bearer <token>` header.
+///
+/// Returns an error reply if the session is invalid or the user is not an admin.
+fn admin_state(
+    server: &SharedServer,
+    authorization: Option<String>,
+) -> Result<SharedState, Box<dyn Reply>> {
+    authorized_state(server, authorization, Permission::Admin)
+}
+
+/// Get the game of a session with the given permission, by the `Authorization: Bearer <token>`
+/// header.
+///
+/// Returns an error reply if the session is invalid or the user is not allowed.
+fn authorized_state(
+    server: &SharedServer,
+    authorization: Option<String>,
+    permission: Permission,
+) -> Result<SharedState, Box<dyn Reply>> {
+    // Get session from bearer token
+    let session = match authorization
+        .as_deref()
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .and_then(|token| server.sessions.get(token.trim()))
+    {
+        Some(session) => session,
+        None => {
+            return Err(Box::new(
+                crate::web::ApiError::from(crate::lang::SESSION_INVALID).to_reply(),
+            ));
+        }
+    };
+
+    // Find session game
+    let state = match server.game(&session.game) {
+        Some(state) => state,
+        None => {
+            return Err(Box::new(
+                crate::web::ApiError::from(crate::lang::GAME_UNKNOWN).to_reply(),
+            ));
+        }
+    };
+
+    // User must have permission
+    if !state.config.user_can(session.user_id, permission) {
+        let msg = match permission {
+            Permission::Admin => crate::lang::USER_NOT_ADMIN,
+            _ => crate::lang::USER_NOT_ALLOWED,
+        };
+        return Err(Box::new(crate::web::ApiError::from(msg).to_reply()));
+    }
+
+    Ok(state.clone())
+}
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+use warp::http::header::CONTENT_TYPE;
+use warp::reply::{with_header, Reply};
+
+use crate::config::Permission;
+use crate::game::code;
+use crate::state::SharedServer;
+use crate::web::ApiError;
+
+/// QR code image format.
+#[derive(Debug, Clone, Copy)]
+pub enum QrFormat {
+    Svg,
+    Png,
+}
+
+/// Render the current token of an outpost as QR code.
+///
+/// Requires a session token of a user allowed to show the outpost code in the
+/// `Authorization: Bearer <token>` header.
+pub fn qr(
+    outpost_id: u32,
+    format: QrFormat,
+    query: QrQuery,
+    authorization: Option<String>,
+    server: SharedServer,
+) -> Box<dyn Reply> {
+    let state =
+        match super::authorized_state(&server, authorization, Permission::Outpost(outpost_id)) {
+            Ok(state) => state,
+            Err(reply) => return reply,
+        };
+
+    // Get current or static token of known outpost
+    if !state.config.outposts.known(outpost_id) {
+        return Box::new(ApiError::from(crate::lang::OUTPOST_UNKNOWN).to_reply());
+    }
+    let token = if query.r#static {
+        match code::get_static_outpost_token(&state.config, outpost_id) {
+            Some(token) => token,
+            None => {
+                return Box::new(ApiError::from(crate::lang::OUTPOST_NOT_STATIC).to_reply());
+            }
+        }
+    } else {
+        code::get_outpost_token(&state.config, outpost_id)
+    };
+
+    // Render QR code
+    let reply: Option<Box<dyn Reply>> = match format {
+        QrFormat::Svg => crate::qr::svg(&token)
+            .map(|svg| Box::new(with_header(svg, CONTENT_TYPE, "image/svg+xml")) as _),
+        QrFormat::Png => crate::qr::png(&token)
+            .map(|png| Box::new(with_header(png, CONTENT_TYPE, "image/png")) as _),
+    };
+    reply.unwrap_or_else(|| Box::new(ApiError::from(crate::lang::INTERNAL_ERROR).to_reply()))
+}
+
+/// QR code query.
+#[derive(Deserialize, Debug)]
+pub struct QrQuery {
+    /// Whether to render the static token, that never expires.
+    #[serde(default)]
+    r#static: bool,
+}
@@ -4,43 +4,45 @@ pub mod magic;
 use std::convert::Infallible;
 use std::error::Error;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use warp::http::StatusCode;
 use warp::{Filter, Rejection, Reply};
 
-use crate::state::SharedState;
+use crate::state::SharedServer;
 
 /// Build routes.
 pub fn routes(
-    state: SharedState,
+    server: SharedServer,
 ) -> impl Filter<Extract = impl Reply, Error = Infallible> + Clone + Send + Sync + 'static {
     // Helper to transform state in shareable warp filter.
-    let s = |s: SharedState| warp::any().map(move || s.clone());
+    let s = |s: SharedServer| warp::any().map(move || s.clone());
 
     let heartbeat = warp::path("__heartbeat__").map(magic::heartbeat);
 
     let api_auth_users = warp::path("users")
-        .and(s(state.clone()))
-        .map(|state: SharedState| api::auth::users(state));
+        .and(warp::query())
+        .and(s(server.clone()))
+        .map(api::auth::users);
 
     let api_auth_login = warp::path("login").and(
         warp::post()
             .and(warp::body::json())
-            .and(s(state.clone()))
+            .and(warp::header::optional::<String>("user-agent"))
+            .and(s(server.clone()))
             .map(api::auth::login),
     );
 
     let api_auth_logout = warp::path("logout").and(
         warp::post()
             .and(warp::body::json())
-            .and(s(state.clone()))
+            .and(s(server.clone()))
             .map(api::auth::logout),
     );
 
     let api_auth_validate = warp::path("validate").and(
         warp::post()
             .and(warp::body::json())
-            .and(s(state.clone()))
+            .and(s(server.clone()))
             .map(api::auth::validate),
     );
 
@@ -51,18 +53,84 @@ pub fn routes(
             .or(api_auth_validate),
     );
 
-    let api = warp::path("api").and(api_auth.recover(handle_api_rejection));
+    let api_games = warp::path("games")
+        .and(s(server.clone()))
+        .map(api::games::games);
+
+    let api_history = warp::path("history")
+        .and(warp::get())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(s(server.clone()))
+        .map(api::history::history);
+
+    let api_audit = warp::path("audit.csv")
+        .and(warp::get())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(s(server.clone()))
+        .map(api::audit::audit_csv);
+
+    let api_outpost_qr_svg = warp::path!("outposts" / u32 / "qr.svg")
+        .and(warp::get())
+        .map(|id| (id, api::outposts::QrFormat::Svg))
+        .untuple_one();
+    let api_outpost_qr_png = warp::path!("outposts" / u32 / "qr.png")
+        .and(warp::get())
+        .map(|id| (id, api::outposts::QrFormat::Png))
+        .untuple_one();
+    let api_outpost_qr = api_outpost_qr_svg
+        .or(api_outpost_qr_png)
+        .unify()
+        .and(warp::query())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(s(server.clone()))
+        .map(api::outposts::qr);
+
+    let api = warp::path("api").and(
+        api_auth
+            .or(api_games)
+            .or(api_history)
+            .or(api_audit)
+            .or(api_outpost_qr)
+            .recover(handle_api_rejection),
+    );
+
+    let ws_spectate = warp::path!("ws" / "spectate")
+        .and(warp::ws())
+        .and(warp::query())
+        .and(s(server.clone()))
+        .map(
+            |ws: warp::ws::Ws, query: SpectateQuery, server: SharedServer| -> Box<dyn Reply> {
+                // Find game, spectating must be enabled
+                let state = match server.game_or_single(query.game.as_deref()) {
+                    Some(state) => state.clone(),
+                    None => {
+                        return Box::new(
+                            crate::web::ApiError::from(crate::lang::GAME_UNKNOWN).to_reply(),
+                        )
+                    }
+                };
+                if !state.config.spectate.enabled {
+                    return Box::new(
+                        crate::web::ApiError::from(crate::lang::SPECTATE_DISABLED).to_reply(),
+                    );
+                }
+
+                Box::new(ws.on_upgrade(move |socket| crate::ws::spectate::connected(state, socket)))
+            },
+        );
 
     let ws =
         warp::path("ws")
             .and(warp::ws())
-            .and(s(state.clone()))
-            .map(|ws: warp::ws::Ws, state| {
+            .and(s(server.clone()))
+            .map(|ws: warp::ws::Ws, server| {
                 // Start handling socket when websocket handshake succeeds
-                ws.on_upgrade(move |socket| crate::ws::connected(state, socket))
+                ws.on_upgrade(move |socket| crate::ws::connected(server, socket))
             });
 
-    let static_sprites = warp::path("sprites").and(warp::fs::dir("../sprites"));
+    let static_sprites = warp::path("sprites").and(warp::fs::dir(crate::SPRITES_PATH));
 
     let static_client = warp::fs::dir("../client/dist");
 
@@ -70,6 +138,7 @@ pub fn routes(
 
     heartbeat
         .or(api)
+        .or(ws_spectate)
         .or(ws)
         .or(static_sprites)
         .or(static_client)
@@ -147,6 +216,13 @@ async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
     Ok(warp::reply::with_status(message, code))
 }
 
+/// Spectate query.
+#[derive(Deserialize, Debug)]
+struct SpectateQuery {
+    #[serde(default)]
+    game: Option<String>,
+}
+
 /// An API error serializable to JSON.
 #[derive(Serialize, Debug)]
 pub(crate) struct ErrorMessage {
@@ -0,0 +1,143 @@
+//! Crash-safe save file handling.
+//!
+//! Save files are written atomically, so a crash or full disk while saving never corrupts the
+//! existing save. Timestamped backups of earlier saves are kept to recover from if loading the
+//! primary save file still fails.
+
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Suffix of temporary files being written.
+const TMP_SUFFIX: &str = ".tmp";
+
+/// Suffix of backup files.
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// Atomically write data to a file.
+///
+/// Writes to a temporary file next to it first, syncs it to disk, and then renames it over the
+/// original. The original is left intact if anything fails.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = sibling(path, TMP_SUFFIX);
+
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    // Sync directory to persist the rename, not supported on all platforms
+    if let Some(dir) = parent(path) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a timestamped backup of a save file, keeping the `keep` newest backups.
+pub fn backup(path: &Path, keep: usize) -> io::Result<()> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let backup = sibling(path, &format!(".{}{}", millis, BACKUP_SUFFIX));
+
+    // Copy through atomic write, so a backup is never partial
+    let data = fs::read(path)?;
+    write_atomic(&backup, &data)?;
+
+    // Remove old backups
+    for old in backups(path).into_iter().skip(keep) {
+        trace!("Removing old backup {}", old.display());
+        fs::remove_file(old)?;
+    }
+
+    Ok(())
+}
+
+/// List backups of a save file, newest first.
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => format!("{}.", name),
+        None => return vec![],
+    };
+    let dir = parent(path).unwrap_or_else(|| Path::new("."));
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    // Find backups by name, order by timestamp
+    let mut backups: Vec<(u128, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let timestamp = file_name
+                .to_str()?
+                .strip_prefix(&name)?
+                .strip_suffix(BACKUP_SUFFIX)?
+                .parse()
+                .ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+    backups.sort_unstable_by_key(|(timestamp, _)| Reverse(*timestamp));
+    backups.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Load and parse a save file, falling back to the newest valid backup.
+///
+/// Returns `Ok(None)` if the save file doesn't exist. Returns an error if the save file and
+/// all backups fail to load.
+pub fn load<T, F>(path: &Path, parse: F) -> Result<Option<T>, ()>
+where
+    F: Fn(&[u8]) -> Result<T, String>,
+{
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    for (i, path) in iter::once(path.to_path_buf())
+        .chain(backups(path))
+        .enumerate()
+    {
+        let result = fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| parse(&data));
+        match result {
+            Ok(value) if i == 0 => return Ok(Some(value)),
+            Ok(value) => {
+                warn!("Loaded backup {} instead", path.display());
+                return Ok(Some(value));
+            }
+            Err(err) => error!("Failed to load {}: {}", path.display(), err),
+        }
+    }
+
+    error!("Failed to load save file or any of its backups");
+    Err(())
+}
+
+/// Path of a file next to the given one, with a suffix appended to its name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Get parent directory of a path, `None` if it has none.
+fn parent(path: &Path) -> Option<&Path> {
+    path.parent().filter(|p| !p.as_os_str().is_empty())
+}
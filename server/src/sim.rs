@@ -0,0 +1,431 @@
+//! Headless game balance simulator.
+//!
+//! Runs the real game logic without web server, with bots playing a number of teams using simple
+//! strategies. Used to validate the item economy of a configuration before an event, rather than
+//! during it.
+
+use std::collections::{HashMap, HashSet};
+
+use clap::{Args, ValueEnum};
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::config::{Config, ConfigItem};
+use crate::game::{Game, GameRng};
+use crate::types::{Amount, ItemRef};
+
+/// Number of outposts teams scan in the simulation, if no outposts are configured.
+const OUTPOST_COUNT: u32 = 10;
+
+/// Simulation options.
+#[derive(Args, Debug, Clone)]
+pub struct Options {
+    /// Number of teams to simulate.
+    #[arg(long, default_value_t = 10)]
+    pub teams: u32,
+
+    /// Number of game ticks to simulate.
+    #[arg(long, default_value_t = 3600)]
+    pub ticks: u64,
+
+    /// Ticks between actions of each team.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub action_interval: u64,
+
+    /// Ticks between outpost scans of each team, 0 to never scan.
+    #[arg(long, default_value_t = 300)]
+    pub scan_interval: u64,
+
+    /// Bot strategies, assigned to teams in turn.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "merger,seller,buyer"
+    )]
+    pub strategy: Vec<Strategy>,
+
+    /// Ticks between money and energy samples.
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub sample_interval: u64,
+
+    /// Random seed, defaults to the configured seed or a random seed.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// Bot strategy.
+#[derive(ValueEnum, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Greedily merge items, never buy or sell.
+    Merger,
+
+    /// Merge items, sell items that can't be merged and anything when running out of space.
+    Seller,
+
+    /// Merge items, buy the most expensive affordable item into free cells.
+    Buyer,
+}
+
+impl Strategy {
+    /// Let the bot take a single action for a team.
+    ///
+    /// Returns `false` if there was nothing to do.
+    fn act(self, game: &Game, config: &Config, user_id: u32) -> bool {
+        match self {
+            Strategy::Merger => merge(game, config, user_id),
+            Strategy::Seller => merge(game, config, user_id) || sell(game, config, user_id),
+            Strategy::Buyer => merge(game, config, user_id) || buy(game, config, user_id),
+        }
+    }
+}
+
+/// Simulation report.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// Seed the game was simulated with.
+    pub seed: u64,
+
+    /// Number of simulated ticks.
+    pub ticks: u64,
+
+    /// Final state of each team.
+    pub teams: Vec<TeamReport>,
+
+    /// Money and energy of each team over time, in team order.
+    pub samples: Vec<Sample>,
+
+    /// Discovery times of each item, ordered by tier and level.
+    pub items: Vec<ItemReport>,
+}
+
+/// Final state of a simulated team.
+#[derive(Serialize, Debug)]
+pub struct TeamReport {
+    pub id: u32,
+    pub strategy: Strategy,
+    pub money: u64,
+    pub energy: u64,
+    pub discovered: usize,
+}
+
+/// Money and energy of all teams at a tick.
+#[derive(Serialize, Debug)]
+pub struct Sample {
+    pub tick: u64,
+    pub money: Vec<u64>,
+    pub energy: Vec<u64>,
+}
+
+/// Discovery times of an item.
+#[derive(Serialize, Debug)]
+pub struct ItemReport {
+    pub item: ItemRef,
+    pub name: String,
+
+    /// Ticks at which each team that discovered the item did so, sorted.
+    pub discovered: Vec<u64>,
+}
+
+/// Run a simulation.
+pub fn run(config: &Config, options: &Options) -> Report {
+    let seed = options
+        .seed
+        .or(config.game.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = GameRng::seed_from_u64(seed);
+
+    // Create game with all teams
+    let game = Game::default();
+    game.reset_seeded(seed);
+    game.set_running(true);
+    let teams: Vec<(u32, Strategy)> = (1..=options.teams)
+        .zip(options.strategy.iter().cycle().cloned())
+        .collect();
+    for (id, _) in &teams {
+        game.ensure_user(config, *id);
+    }
+
+    // Outposts to scan, the configured ones unless any is accepted
+    let outposts: Vec<u32> = if config.outposts.any() {
+        (1..=OUTPOST_COUNT).collect()
+    } else {
+        config.outposts.list.iter().map(|o| o.id).collect()
+    };
+
+    let mut samples = vec![];
+    let mut discovered: HashMap<ItemRef, Vec<u64>> = HashMap::new();
+    let mut seen: HashMap<u32, HashSet<ItemRef>> = HashMap::new();
+    for tick in 0..=options.ticks {
+        if tick > 0 {
+            game.tick_step(config);
+        }
+
+        for (id, strategy) in &teams {
+            // Act in turn, spread over the action interval
+            if (tick + *id as u64).is_multiple_of(options.action_interval) {
+                strategy.act(&game, config, *id);
+            }
+
+            // Scan a random outpost, other than the last one
+            if options.scan_interval > 0 && tick > 0 && tick % options.scan_interval == 0 {
+                let last = game.users.read().unwrap()[id]
+                    .read()
+                    .unwrap()
+                    .outposts
+                    .back()
+                    .cloned();
+                let candidates: Vec<u32> = outposts
+                    .iter()
+                    .cloned()
+                    .filter(|outpost| Some(*outpost) != last)
+                    .collect();
+                if !candidates.is_empty() {
+                    let outpost = candidates[rng.gen_range(0..candidates.len())];
+                    game.user_scan_code(*id, config, Some(outpost));
+                }
+            }
+        }
+
+        // Track newly discovered items
+        let users = game.users.read().unwrap();
+        for (id, _) in &teams {
+            let user = users[id].read().unwrap();
+            let seen = seen.entry(*id).or_default();
+            for item in user.inventory.discovered.difference(seen) {
+                discovered.entry(item.clone()).or_default().push(tick);
+            }
+            seen.extend(user.inventory.discovered.iter().cloned());
+        }
+
+        // Sample money and energy
+        if tick % options.sample_interval == 0 || tick == options.ticks {
+            let users = teams.iter().map(|(id, _)| users[id].read().unwrap());
+            let (money, energy) = users
+                .map(|user| (user.inventory.money, user.inventory.energy))
+                .unzip();
+            samples.push(Sample {
+                tick,
+                money,
+                energy,
+            });
+        }
+    }
+
+    // Collect final team states
+    let users = game.users.read().unwrap();
+    let teams = teams
+        .iter()
+        .map(|(id, strategy)| {
+            let user = users[id].read().unwrap();
+            TeamReport {
+                id: *id,
+                strategy: *strategy,
+                money: user.inventory.money,
+                energy: user.inventory.energy,
+                discovered: user.inventory.discovered.len(),
+            }
+        })
+        .collect();
+
+    // Collect discovery times of all items
+    let mut items: Vec<&ConfigItem> = config.items.values().collect();
+    items.sort_by_key(|i| (i.id.tier_level(), i.id.to_string()));
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let mut discovered = discovered.remove(&item.id).unwrap_or_default();
+            discovered.sort_unstable();
+            ItemReport {
+                item: item.id.clone(),
+                name: item.name.clone(),
+                discovered,
+            }
+        })
+        .collect();
+
+    Report {
+        seed,
+        ticks: options.ticks,
+        teams,
+        samples,
+        items,
+    }
+}
+
+/// Snapshot of the items in each inventory cell of a team.
+fn cells(game: &Game, user_id: u32) -> Vec<Option<ItemRef>> {
+    let users = game.users.read().unwrap();
+    let user = users[&user_id].read().unwrap();
+    user.inventory
+        .grid
+        .items
+        .iter()
+        .map(|item| item.as_ref().map(|item| item.id.clone()))
+        .collect()
+}
+
+/// Check whether a team has the given amounts.
+fn has_amounts(game: &Game, user_id: u32, amounts: &[Amount]) -> bool {
+    let users = game.users.read().unwrap();
+    let user = users[&user_id].read().unwrap();
+    user.inventory.has_amounts(amounts)
+}
+
+/// Merge the highest level pair of mergeable items the team can pay for.
+fn merge(game: &Game, config: &Config, user_id: u32) -> bool {
+    let cells = cells(game, user_id);
+    let mut pairs: Vec<(&ConfigItem, u8, u8)> = cells
+        .iter()
+        .enumerate()
+        .filter_map(|(cell, item)| {
+            let item = config.item(item.as_ref()?)?;
+            item.merge.as_ref()?;
+            let other = cells
+                .iter()
+                .skip(cell + 1)
+                .position(|other| other.as_ref() == Some(&item.id))?;
+            Some((item, cell as u8, (cell + 1 + other) as u8))
+        })
+        .collect();
+    pairs.sort_by_key(|(item, cell, _)| (item.id.level(), *cell));
+
+    while let Some((item, cell, other)) = pairs.pop() {
+        let costs = item
+            .merge_cost
+            .as_ref()
+            .or(config.defaults.merge_cost.as_ref());
+        if let Some(costs) = costs {
+            if !has_amounts(game, user_id, costs) || game.user_pay(user_id, config, costs).is_err()
+            {
+                continue;
+            }
+        }
+        return game.user_merge(user_id, config, cell, other).is_some();
+    }
+    false
+}
+
+/// Sell the most valuable item that can't be merged, or any item when running out of space.
+///
+/// Factories are never sold.
+fn sell(game: &Game, config: &Config, user_id: u32) -> bool {
+    let cells = cells(game, user_id);
+    let low_space = cells.iter().filter(|item| item.is_none()).count() < 2;
+    let cell = cells
+        .iter()
+        .enumerate()
+        .filter_map(|(cell, item)| Some((cell, config.item(item.as_ref()?)?)))
+        .filter(|(_, item)| item.drop_interval.is_none() && item.sell > 0)
+        .filter(|(_, item)| low_space || item.merge.is_none())
+        .max_by_key(|(cell, item)| (item.sell, *cell))
+        .map(|(cell, _)| cell as u8);
+
+    match cell {
+        Some(cell) => game.user_sell(user_id, config, cell).is_some(),
+        None => false,
+    }
+}
+
+/// Buy the most expensive affordable item into a free cell.
+fn buy(game: &Game, config: &Config, user_id: u32) -> bool {
+    let cell = match cells(game, user_id).iter().position(Option::is_none) {
+        Some(cell) => cell as u8,
+        None => return false,
+    };
+
+    let mut items: Vec<(&ConfigItem, &Vec<Amount>)> = config
+        .items
+        .values()
+        .filter_map(|item| Some((item, item.buy.as_ref()?)))
+        .filter(|(_, costs)| has_amounts(game, user_id, costs))
+        .collect();
+    items.sort_by_key(|(item, costs)| (amounts_money(costs), item.id.tier_level()));
+
+    match items.pop() {
+        Some((item, costs)) => {
+            game.user_pay(user_id, config, costs).is_ok()
+                && game.user_buy(user_id, config, cell, item.clone()).is_some()
+        }
+        None => false,
+    }
+}
+
+/// Sum money in list of amounts.
+fn amounts_money(amounts: &[Amount]) -> u64 {
+    amounts
+        .iter()
+        .map(|amount| match amount {
+            Amount::Money { money } => *money,
+            _ => 0,
+        })
+        .sum()
+}
+
+impl Report {
+    /// Print a human readable report.
+    pub fn print(&self) {
+        println!(
+            "Simulated {} teams for {} ticks with seed {}",
+            self.teams.len(),
+            self.ticks,
+            self.seed
+        );
+
+        println!("\nMoney and energy over time (min / median / max):");
+        println!("{:>8}  {:>26}  {:>26}", "tick", "money", "energy");
+        for sample in &self.samples {
+            println!(
+                "{:>8}  {:>26}  {:>26}",
+                sample.tick,
+                spread(&sample.money),
+                spread(&sample.energy)
+            );
+        }
+
+        println!("\nTicks to discover items (teams, first / median / last):");
+        for item in &self.items {
+            let ticks = if item.discovered.is_empty() {
+                "never".into()
+            } else {
+                spread(&item.discovered)
+            };
+            println!(
+                "{:>8}  {:<24}  {:>3}/{:<3}  {}",
+                item.item.to_string(),
+                item.name,
+                item.discovered.len(),
+                self.teams.len(),
+                ticks
+            );
+        }
+
+        println!("\nFinal leaderboard:");
+        let mut teams: Vec<&TeamReport> = self.teams.iter().collect();
+        teams.sort_by_key(|team| (std::cmp::Reverse(team.money), team.id));
+        for (rank, team) in teams.iter().enumerate() {
+            println!(
+                "{:>4}. team {:<4} {:<8} money {:>8}  energy {:>6}  discovered {:>3}",
+                rank + 1,
+                team.id,
+                format!("{:?}", team.strategy).to_lowercase(),
+                team.money,
+                team.energy,
+                team.discovered
+            );
+        }
+        let money: Vec<u64> = self.teams.iter().map(|team| team.money).collect();
+        println!("Money distribution: {}", spread(&money));
+    }
+}
+
+/// Format the minimum, median and maximum of a list of values.
+fn spread(values: &[u64]) -> String {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+    match (values.first(), values.last()) {
+        (Some(min), Some(max)) => format!("{} / {} / {}", min, values[values.len() / 2], max),
+        _ => "-".into(),
+    }
+}
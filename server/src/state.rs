@@ -1,38 +1,203 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use crate::auth::{ClientManager, SessionManager};
+use serde_json::Value;
+
+use crate::audit::{self, AuditEntry, AuditLog};
+use crate::auth::{ClientManager, Session, SessionManager};
+use crate::config::registry::RegistryGame;
 use crate::config::Config;
+use crate::game::code::{self, RedeemedTokens};
+use crate::game::journal::{self, Journal};
 use crate::game::Game;
 
+pub type SharedServer = Arc<Server>;
 pub type SharedState = Arc<State>;
 
-/// Shared server state.
+/// Shared server state, hosting one or more games.
+pub struct Server {
+    pub sessions: SessionManager,
+    pub games: Vec<SharedState>,
+}
+
+impl Server {
+    /// Construct new server state.
+    pub fn new(sessions: SessionManager, games: Vec<SharedState>) -> Self {
+        Server { sessions, games }
+    }
+
+    /// Get a game by ID.
+    pub fn game(&self, id: &str) -> Option<&SharedState> {
+        self.games.iter().find(|g| g.id == id)
+    }
+
+    /// Get a game by ID, or the only game if no ID is given.
+    ///
+    /// Returns `None` if no ID is given while hosting multiple games.
+    pub fn game_or_single(&self, id: Option<&str>) -> Option<&SharedState> {
+        match id {
+            Some(id) => self.game(id),
+            None if self.games.len() == 1 => self.games.first(),
+            None => None,
+        }
+    }
+
+    /// Revoke the given sessions, forcibly closing their connected clients.
+    fn close_sessions(&self, sessions: &[Session]) {
+        for session in sessions {
+            let closed = self
+                .game(&session.game)
+                .map(|state| state.clients.close_session(&session.id))
+                .unwrap_or(0);
+            info!(
+                "Revoked session {} (game: {}, user: {}), closed {} client(s)",
+                session.id, session.game, session.user_id, closed
+            );
+        }
+    }
+
+    /// Revoke a session in a game by its public ID.
+    ///
+    /// Returns the user ID of the revoked session, `None` if the session wasn't found.
+    pub fn revoke_session(&self, game: &str, session_id: &str) -> Option<u32> {
+        let sessions = self
+            .sessions
+            .retain(|s| !(s.game == game && s.id == session_id));
+        self.close_sessions(&sessions);
+        sessions.first().map(|session| session.user_id)
+    }
+
+    /// Revoke all sessions of a user in a game.
+    ///
+    /// Returns the number of revoked sessions.
+    pub fn revoke_user_sessions(&self, game: &str, user_id: u32) -> usize {
+        let sessions = self
+            .sessions
+            .retain(|s| !(s.game == game && s.user_id == user_id));
+        self.close_sessions(&sessions);
+        sessions.len()
+    }
+
+    /// Prune expired and idle sessions, and sessions for games that are no longer hosted.
+    pub fn prune_sessions(&self) {
+        let now = crate::auth::now();
+        let sessions = self.sessions.retain(|session| {
+            let state = match self.game(&session.game) {
+                Some(state) => state,
+                None => return false,
+            };
+            let idle = state
+                .config
+                .sessions
+                .max_idle()
+                .map(|max| session.last_seen + max.as_secs() <= now)
+                .unwrap_or(false);
+            !session.is_expired(now) && !idle
+        });
+        self.close_sessions(&sessions);
+    }
+
+    /// Transform into shared state.
+    pub fn shared(self) -> SharedServer {
+        Arc::new(self)
+    }
+}
+
+/// Shared game state.
 pub struct State {
+    /// Game ID.
+    pub id: String,
+
+    /// Game display name.
+    pub name: String,
+
     pub config: Config,
-    pub sessions: SessionManager,
     pub clients: ClientManager,
     pub game: Game,
+
+    /// Audit log of admin actions.
+    pub audit: AuditLog,
+
+    /// Single-use outpost tokens redeemed by users.
+    pub tokens: RedeemedTokens,
+
+    /// Game save file path.
+    pub game_path: PathBuf,
 }
 
 impl State {
     /// Construct new state.
-    pub fn new(config: Config) -> Self {
-        // Load game
+    pub fn new(registry: &RegistryGame, config: Config, game_path: PathBuf) -> Self {
+        // Load game, new games are reset to seed them, a journal without save is caught up with
+        let journal_path = journal::path_for(&game_path);
+        let reset = config.game.reset || (!game_path.is_file() && !journal_path.is_file());
         let mut game = if config.game.reset {
-            info!("Resetting game state according to configuration");
+            info!(
+                "Resetting game state of '{}' according to configuration",
+                registry.id
+            );
             Game::default()
         } else {
-            Game::load(&config).expect("failed to load game state")
+            Game::load(&config, &game_path).expect("failed to load game state")
         };
 
+        // Catch up with events journaled after the last save, such as after a crash
+        if game.tick() > 0 && !journal_path.is_file() {
+            warn!("Game has no journal since its start, it can't be fully replayed");
+        }
+        if !reset && journal_path.is_file() {
+            match journal::read(&journal_path) {
+                Ok(events) => {
+                    let count = game.catch_up(&config, &events);
+                    if count > 0 {
+                        info!("Caught up {} game events journaled since last save", count);
+                    }
+                }
+                Err(err) => error!("Failed to read game journal to catch up: {}", err),
+            }
+        }
+
+        // Record events to journal, mark reset in journal
+        game.set_journal(Journal::open(&journal_path).expect("failed to open game journal"));
+        if reset {
+            game.reset(&config);
+        }
+        let audit = AuditLog::open(&audit::path_for(&game_path)).expect("failed to open audit log");
+        let tokens = RedeemedTokens::load(code::path_for(&game_path))
+            .expect("failed to load redeemed tokens");
+
         State {
+            id: registry.id.clone(),
+            name: registry.name().into(),
             config,
-            sessions: SessionManager::load().expect("failed to load session manager"),
             clients: ClientManager::new(),
             game,
+            audit,
+            tokens,
+            game_path,
         }
     }
 
+    /// Record an admin action in the audit log.
+    pub fn audit(
+        &self,
+        admin: u32,
+        action: &str,
+        target: Option<u32>,
+        before: Value,
+        after: Value,
+    ) {
+        self.audit.record(AuditEntry {
+            time: crate::auth::now(),
+            tick: self.game.tick(),
+            admin,
+            action: action.into(),
+            target,
+            before,
+            after,
+        });
+    }
+
     /// Transform into shared state.
     pub fn shared(self) -> SharedState {
         Arc::new(self)
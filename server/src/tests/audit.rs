@@ -0,0 +1,65 @@
+use std::fs;
+
+use serde_json::json;
+
+use crate::audit::{self, AuditEntry, AuditLog, AuditQuery};
+
+/// Ensure audit entries persist, can be queried, and export to CSV.
+#[test]
+fn audit_log() {
+    let path = std::env::temp_dir().join(format!("mms-test-audit-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let entry = |time, admin, target| AuditEntry {
+        time,
+        tick: time * 10,
+        admin,
+        action: "reward_user".into(),
+        target,
+        before: json!({"money": 1}),
+        after: json!("a, \"quoted\" value"),
+    };
+
+    let log = AuditLog::open(&path).unwrap();
+    log.record(entry(1, 100, Some(1)));
+    log.record(entry(2, 100, Some(2)));
+    log.record(entry(3, 101, None));
+    drop(log);
+
+    // Entries are loaded again on open, and can be filtered
+    let log = AuditLog::open(&path).unwrap();
+    let times = |query| -> Vec<u64> { log.query(&query).iter().map(|e| e.time).collect() };
+    assert_eq!(times(AuditQuery::default()), [1, 2, 3]);
+    assert_eq!(
+        times(AuditQuery {
+            target: Some(2),
+            ..Default::default()
+        }),
+        [2]
+    );
+    assert_eq!(
+        times(AuditQuery {
+            admin: Some(100),
+            since: Some(1),
+            ..Default::default()
+        }),
+        [2]
+    );
+    assert_eq!(
+        times(AuditQuery {
+            limit: Some(2),
+            ..Default::default()
+        }),
+        [2, 3]
+    );
+
+    let csv = audit::to_csv(&log.query(&AuditQuery::default()));
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "time,tick,admin,action,target,before,after");
+    assert_eq!(
+        lines[1],
+        "1,10,100,reward_user,1,\"{\"\"money\"\":1}\",\"a, \"\"quoted\"\" value\""
+    );
+    assert!(lines[3].starts_with("3,30,101,reward_user,,"));
+
+    let _ = fs::remove_file(&path);
+}
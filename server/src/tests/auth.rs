@@ -0,0 +1,72 @@
+use crate::auth;
+
+/// Ensure hashed and plaintext passwords verify.
+#[test]
+fn verify_password() {
+    let hash = auth::hash_password("secret");
+    assert!(auth::is_password_hash(&hash));
+    assert!(auth::is_valid_password_hash(&hash));
+    assert!(auth::verify_password(&hash, "secret"));
+    assert!(!auth::verify_password(&hash, "Secret"));
+
+    // Same password is salted differently
+    assert_ne!(hash, auth::hash_password("secret"));
+
+    // Plaintext passwords are still supported
+    assert!(!auth::is_password_hash("secret"));
+    assert!(auth::verify_password("secret", "secret"));
+    assert!(!auth::verify_password("secret", "secret2"));
+
+    // Broken hashes never verify
+    assert!(!auth::is_valid_password_hash("$argon2id$broken"));
+    assert!(!auth::verify_password(
+        "$argon2id$broken",
+        "$argon2id$broken"
+    ));
+}
+
+/// Ensure sessions expire, and can be listed and revoked.
+#[test]
+fn session_expiry() {
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("mms-test-sessions-{}.json", std::process::id()));
+    let sessions = auth::SessionManager::new(path.clone());
+
+    let session = sessions.add("default", 1, None, Some("phone".into()));
+    let expiring = sessions.add("default", 2, Some(Duration::from_secs(0)), None);
+    let other = sessions.add("other", 1, None, None);
+    assert!(sessions.get(session.token()).is_some());
+    assert!(sessions.get(expiring.token()).is_none());
+    assert_eq!(sessions.list("default", Some(1)).len(), 1);
+
+    // Prune expired sessions, revoke by ID
+    let now = auth::now();
+    let pruned = sessions.retain(|s| !s.is_expired(now));
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].id, expiring.id);
+    assert!(sessions.remove_by_id(&session.id).is_some());
+    assert!(sessions.get(session.token()).is_none());
+    assert!(sessions.get(other.token()).is_some());
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// Ensure a corrupt sessions file falls back to its newest backup.
+#[test]
+fn sessions_backup_fallback() {
+    let path =
+        std::env::temp_dir().join(format!("mms-test-sessions-bak-{}.json", std::process::id()));
+    let sessions = auth::SessionManager::new(path.clone());
+    let session = sessions.add("default", 1, None, None);
+    crate::save::backup(&path, 1).unwrap();
+
+    // Torn sessions file
+    std::fs::write(&path, b"{\"sessions\":[").unwrap();
+    let sessions = auth::SessionManager::load(path.clone()).unwrap();
+    assert!(sessions.get(session.token()).is_some());
+
+    for path in crate::save::backups(&path).into_iter().chain(Some(path)) {
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use rand::SeedableRng;
+
+use crate::config;
+use crate::game::code::{self, RedeemedTokens};
+use crate::game::GameRng;
+
+/// Ensure outpost tokens are signed, expire, and single-use tokens are redeemed once.
+#[test]
+fn outpost_tokens() {
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    config.outposts.token_interval_secs = 30;
+    config.outposts.token_valid_secs = 60;
+    let mut rng = GameRng::seed_from_u64(1);
+    let now = 1_000_010;
+
+    // Token is valid until its validity window ends
+    let token = code::get_outpost_token_at(&config, 5, now, &mut rng);
+    let valid = code::validate_outpost_token_at(&config, &token, now).unwrap();
+    assert_eq!(valid.outpost, 5);
+    assert!(code::validate_outpost_token_at(&config, &token, now + 39).is_some());
+    assert!(code::validate_outpost_token_at(&config, &token, now + 40).is_none());
+    assert!(code::validate_outpost_token_at(&config, &token, now - 30).is_none());
+
+    // Tokens for the same interval have different nonces
+    let other = code::get_outpost_token_at(&config, 5, now, &mut rng);
+    assert_ne!(token, other);
+
+    // Tampered tokens and tokens signed with another secret are rejected
+    let tampered = base64::encode(
+        String::from_utf8(base64::decode(&token).unwrap())
+            .unwrap()
+            .replacen('5', "6", 1),
+    );
+    assert!(code::validate_outpost_token_at(&config, &tampered, now).is_none());
+    config.outposts.secret = "other".into();
+    assert!(code::validate_outpost_token_at(&config, &token, now).is_none());
+
+    // Single-use tokens are redeemed once per user, forgotten when expired
+    let tokens = RedeemedTokens::default();
+    assert!(tokens.redeem(1, &valid, now));
+    assert!(!tokens.redeem(1, &valid, now + 1));
+    assert!(tokens.redeem(2, &valid, now + 1));
+    assert!(tokens.redeem(1, &valid, valid.expires.unwrap()));
+
+    // Released tokens can be redeemed again
+    tokens.release(1, &valid);
+    assert!(tokens.redeem(1, &valid, now));
+
+    // Redeemed tokens persist across restarts
+    let path = std::env::temp_dir().join(format!("mms-test-tokens-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let tokens = RedeemedTokens::load(path.clone()).unwrap();
+    assert!(tokens.redeem(1, &valid, now));
+    let tokens = RedeemedTokens::load(path.clone()).unwrap();
+    assert!(!tokens.redeem(1, &valid, now));
+    assert!(tokens.redeem(2, &valid, now));
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Ensure static outpost tokens only work for outposts that enable them, and render as QR code.
+#[test]
+fn static_outpost_tokens() {
+    use crate::config::ConfigOutpost;
+
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let outpost = |id, static_code| ConfigOutpost {
+        name: format!("Outpost <{}>", id),
+        static_code,
+        ..super::outpost(id)
+    };
+    config.outposts.list = vec![outpost(1, true), outpost(2, false)];
+
+    // Static tokens never expire, and are never redeemed
+    assert!(code::get_static_outpost_token(&config, 2).is_none());
+    let token = code::get_static_outpost_token(&config, 1).unwrap();
+    let valid = code::validate_outpost_token_at(&config, &token, u64::MAX).unwrap();
+    assert_eq!((valid.outpost, valid.expires), (1, None));
+    let tokens = RedeemedTokens::default();
+    assert!(tokens.redeem(1, &valid, 0));
+    assert!(tokens.redeem(1, &valid, 0));
+
+    // Static tokens are rejected once disabled
+    config.outposts.list[0].static_code = false;
+    assert!(code::validate_outpost_token(&config, &token).is_none());
+    config.outposts.list[0].static_code = true;
+
+    // Render QR codes and sheet
+    assert!(crate::qr::svg(&token).unwrap().starts_with("<?xml"));
+    assert!(crate::qr::png(&token).unwrap().starts_with(b"\x89PNG"));
+    let sheet = crate::qr::sheet(&config).unwrap();
+    assert_eq!(sheet.matches("<section>").count(), 1);
+    assert!(sheet.contains("Outpost &lt;1&gt;"));
+}
@@ -1,7 +1,91 @@
+use std::path::Path;
+
 use crate::config;
 
 /// Ensure `config.toml` loads without issues.
 #[test]
 fn load_config() {
-    config::load().expect("failed to load config from file");
+    config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+}
+
+/// Ensure config validation reports every problem.
+#[test]
+fn validate_config() {
+    use crate::config::ConfigError;
+    use crate::types::ItemRef;
+
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let unknown = ItemRef::from(999, 0);
+
+    // Break configuration in multiple ways
+    let user = config.users[0].clone();
+    config.users.push(user.clone());
+    config.defaults.inventory.push(unknown.clone());
+    let item = config.items.get_mut(&ItemRef::from(10, 0)).unwrap();
+    item.merge = Some(unknown.clone());
+    item.drops[0].chance = 2.0;
+    config.round.start = Some("tomorrow".into());
+    config.round.duration = Some("1h".into());
+    config.round.end_tick = Some(100);
+    config.scoring.stats.insert("unknown".into(), 1.0);
+    config.users[1].roles.push("owner".into());
+
+    let errors = config.validate().expect_err("broken config is valid");
+    assert!(errors.contains(&ConfigError::DuplicateUser { user: user.id }));
+    assert!(errors.contains(&ConfigError::UnknownDefaultItem {
+        item: unknown.clone()
+    }));
+    assert!(errors.contains(&ConfigError::UnknownMergeItem {
+        item: ItemRef::from(10, 0),
+        merge: unknown,
+    }));
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, ConfigError::DropChanceSum { .. })));
+    assert!(errors.contains(&ConfigError::InvalidTime {
+        field: "round.start".into()
+    }));
+    assert!(errors.contains(&ConfigError::RoundDurationAndEndTick));
+    assert!(errors.contains(&ConfigError::UnknownScoringStat {
+        stat: "unknown".into()
+    }));
+    assert!(errors.contains(&ConfigError::UnknownRole {
+        user: config.users[1].id,
+        role: "owner".into(),
+    }));
+}
+
+/// Ensure roles grant the expected permissions.
+#[test]
+fn permissions() {
+    use crate::config::Permission;
+
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let mut user = config.users[0].clone();
+    user.role_game = false;
+    user.role_admin = false;
+    user.roles = vec!["outpost:3".into()];
+
+    // Outpost volunteers may only show their own outpost
+    assert!(user.can(Permission::Outpost(3)));
+    assert!(!user.can(Permission::Outpost(4)));
+    assert!(!user.can(Permission::Play));
+    assert!(!user.can(Permission::Referee));
+    assert!(!user.can(Permission::Admin));
+    assert_eq!(user.outposts(), vec![3]);
+
+    // Referees may show any outpost, but not administer
+    user.roles = vec!["referee".into()];
+    assert!(user.can(Permission::Outpost(4)));
+    assert!(!user.can(Permission::Admin));
+
+    // Admins have every permission but play
+    user.roles = vec!["admin".into()];
+    assert!(user.can(Permission::Referee));
+    assert!(user.can(Permission::Outpost(4)));
+    assert!(!user.can(Permission::Play));
+    user.role_game = true;
+    assert!(user.can(Permission::Play));
 }
@@ -0,0 +1,704 @@
+use std::path::{Path, PathBuf};
+
+use crate::client::ClientActionTradeOffer;
+use crate::config;
+use crate::game::journal::{self, Journal, JournalEvent};
+use crate::game::Game;
+
+/// Count the number of items in a user inventory.
+fn item_count(game: &Game, user_id: u32) -> usize {
+    let users = game.users.read().unwrap();
+    let user = users.get(&user_id).unwrap().read().unwrap();
+    user.inventory
+        .grid
+        .items
+        .iter()
+        .filter(|i| i.is_some())
+        .count()
+}
+
+/// Get money of a user.
+fn money(game: &Game, user_id: u32) -> u64 {
+    let users = game.users.read().unwrap();
+    let user = users.get(&user_id).unwrap().read().unwrap();
+    user.inventory.money
+}
+
+/// Get the full serialized game state.
+///
+/// Sets are sorted, as their serialized order is not stable. Wall clock time is excluded.
+fn state(game: &Game) -> serde_json::Value {
+    // Serialize to string first, random source state has numbers not supported by values
+    let mut state: serde_json::Value =
+        serde_json::from_str(&serde_json::to_string(game).unwrap()).unwrap();
+    state.as_object_mut().unwrap().remove("tick_time");
+    state.as_object_mut().unwrap().remove("journaled");
+    for user in state["users"].as_object_mut().unwrap().values_mut() {
+        for set in &["/inventory/discovered", "/outposts", "/missions"] {
+            let set = user.pointer_mut(set).unwrap().as_array_mut().unwrap();
+            set.sort_unstable_by_key(|value| value.to_string());
+        }
+    }
+    state
+}
+
+/// Temporary game journal file, removed when dropped.
+struct TempJournal {
+    path: PathBuf,
+}
+
+impl TempJournal {
+    /// Read all journaled events.
+    fn events(&self) -> Vec<JournalEvent> {
+        journal::read(&self.path).unwrap()
+    }
+}
+
+impl Drop for TempJournal {
+    fn drop(&mut self) {
+        let _ = journal::remove(&self.path);
+    }
+}
+
+/// Create a game that journals to a new temporary file, named after the test.
+fn game_with_journal(name: &str) -> (Game, TempJournal) {
+    let path = std::env::temp_dir().join(format!("mms-test-{}-{}.jsonl", name, std::process::id()));
+    let _ = journal::remove(&path);
+    let mut game = Game::default();
+    game.set_journal(Journal::open(&path).unwrap());
+    (game, TempJournal { path })
+}
+
+/// Get first occupied inventory cell of a user.
+fn first_item_cell(game: &Game, user_id: u32) -> u8 {
+    let users = game.users.read().unwrap();
+    let user = users.get(&user_id).unwrap().read().unwrap();
+    user.inventory
+        .grid
+        .items
+        .iter()
+        .position(Option::is_some)
+        .unwrap() as u8
+}
+
+/// Ensure trades hold items in escrow, and move or return them when closed.
+#[test]
+fn trade_escrow() {
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let game = Game::default();
+    game.ensure_user(&config, 1);
+    game.ensure_user(&config, 2);
+    let (items_1, items_2) = (item_count(&game, 1), item_count(&game, 2));
+    let (money_1, money_2) = (money(&game, 1), money(&game, 2));
+
+    // Offering takes items and money into escrow
+    let offer = ClientActionTradeOffer {
+        to: 2,
+        cells: vec![first_item_cell(&game, 1)],
+        money: 10,
+        energy: 0,
+        request: vec![],
+    };
+    let (_, trade) = game.user_trade_offer(1, &config, &offer).unwrap();
+    assert_eq!(item_count(&game, 1), items_1 - 1);
+    assert_eq!(money(&game, 1), money_1 - 10);
+
+    // Offering user cannot accept its own trade, receiving user can
+    assert!(game.user_trade_accept(1, &config, trade.id).is_none());
+    assert_eq!(game.user_trade_accept(2, &config, trade.id), Some(1));
+    assert_eq!(item_count(&game, 2), items_2 + 1);
+    assert_eq!(money(&game, 2), money_2 + 10);
+
+    // Declined trades are returned
+    let offer = ClientActionTradeOffer {
+        cells: vec![first_item_cell(&game, 1)],
+        ..offer
+    };
+    let (_, trade) = game.user_trade_offer(1, &config, &offer).unwrap();
+    assert_eq!(game.user_trade_decline(2, &config, trade.id), Some((1, 2)));
+    assert_eq!(item_count(&game, 1), items_1 - 1);
+    assert_eq!(money(&game, 1), money_1 - 10);
+    assert!(game.user_trade_accept(2, &config, trade.id).is_none());
+}
+
+/// Ensure journaled events replay into the same game state.
+#[test]
+fn journal_replay() {
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let (game, journal) = game_with_journal("journal");
+
+    // Mutate game state
+    game.reset_seeded(42);
+    game.set_running(true);
+    game.ensure_user(&config, 1);
+    game.user_scan_code(1, &config, Some(1)).unwrap();
+    game.tick_step(&config);
+    game.user_sell(1, &config, first_item_cell(&game, 1))
+        .unwrap();
+    game.user_scan_code(1, &config, None).unwrap();
+    game.tick_step(&config);
+
+    // Replay all, and up to before the first tick
+    let events = journal.events();
+    let replayed = Game::replay(&config, &events, None);
+    assert!(replayed.running());
+    assert_eq!(replayed.tick(), 2);
+    assert_eq!(state(&replayed), state(&game));
+    let replayed = Game::replay(&config, &events, Some(0));
+    assert_eq!(replayed.tick(), 0);
+    assert!(money(&replayed, 1) < money(&game, 1));
+}
+
+/// Ensure events journaled after the last save are caught up with after a crash.
+#[test]
+fn journal_crash_catch_up() {
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let save_path =
+        std::env::temp_dir().join(format!("mms-test-crash-{}.json", std::process::id()));
+    let (mut game, journal) = game_with_journal("crash");
+
+    // Play and save, then keep playing until crashing
+    game.reset_seeded(42);
+    game.set_running(true);
+    game.ensure_user(&config, 1);
+    game.user_scan_code(1, &config, Some(1)).unwrap();
+    game.tick_step(&config);
+    game.save(&save_path).unwrap();
+    game.user_sell(1, &config, first_item_cell(&game, 1))
+        .unwrap();
+    game.ensure_user(&config, 2);
+    game.tick_step(&config);
+    game.user_scan_code(2, &config, None).unwrap();
+    game.tick_step(&config);
+
+    // Load last save, catch up with journal and continue playing
+    let mut loaded = Game::load(&config, &save_path).unwrap();
+    assert_eq!(loaded.tick(), 1);
+    let events = journal.events();
+    assert_eq!(loaded.catch_up(&config, &events), 5);
+    assert_eq!(state(&loaded), state(&game));
+    loaded.set_journal(Journal::open(&journal.path).unwrap());
+    loaded.tick_step(&config);
+    game.set_journal(Journal::default());
+    game.tick_step(&config);
+
+    // Full journal still replays into the same state
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&loaded));
+    assert_eq!(state(&loaded), state(&game));
+
+    let _ = std::fs::remove_file(&save_path);
+}
+
+/// Ensure games with the same seed and actions end up in the same state.
+#[test]
+fn seeded_determinism() {
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+
+    let play = |seed| {
+        let game = Game::default();
+        game.reset_seeded(seed);
+        for user in 1..=3 {
+            game.ensure_user(&config, user);
+            game.user_scan_code(user, &config, None);
+        }
+        for _ in 0..100 {
+            game.tick_step(&config);
+        }
+        game
+    };
+
+    assert_eq!(state(&play(7)), state(&play(7)));
+    assert_ne!(state(&play(7)), state(&play(8)));
+
+    // Random source continues the same sequence after saving and loading
+    let (game, loaded) = (play(7), play(7));
+    let mut loaded: Game = serde_json::from_str(&serde_json::to_string(&loaded).unwrap()).unwrap();
+    loaded.attach_config(&config).unwrap();
+    game.tick_step(&config);
+    loaded.tick_step(&config);
+    assert_eq!(state(&game), state(&loaded));
+}
+
+/// Ensure the game clock catches up missed ticks, up to the configured maximum.
+#[test]
+fn tick_catch_up() {
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    config.game.tick_millis = 1000;
+    config.game.max_catch_up_ticks = 100;
+    let game = Game::default();
+
+    // Clock starts on first check, then counts whole ticks
+    assert_eq!(game.take_due_ticks(&config, 10_000), 0);
+    assert_eq!(game.take_due_ticks(&config, 12_500), 2);
+    assert_eq!(game.next_tick_in(&config, 12_500), 500);
+    assert_eq!(game.take_due_ticks(&config, 12_900), 0);
+    assert_eq!(game.take_due_ticks(&config, 13_000), 1);
+
+    // Long stalls are limited, and don't pile up
+    assert_eq!(game.take_due_ticks(&config, 513_200), 100);
+    assert_eq!(game.next_tick_in(&config, 513_200), 800);
+    assert_eq!(game.take_due_ticks(&config, 514_000), 1);
+}
+
+/// Ensure timed rounds start, pause and finish on schedule, with a frozen scoreboard, and replay.
+#[test]
+fn timed_round() {
+    use crate::config::ConfigWindow;
+    use crate::game::round::RoundChange;
+
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    config.round.start = Some("2030-01-01T10:00:00Z".into());
+    config.round.end_tick = Some(3);
+    config.round.pauses = vec![ConfigWindow {
+        start: "2030-01-01T11:00:00Z".into(),
+        end: "2030-01-01T11:30:00Z".into(),
+    }];
+    let start = 1_893_492_000;
+    let (game, journal) = game_with_journal("round");
+    game.reset_seeded(1);
+
+    // Wait for scheduled start
+    assert_eq!(game.update_round(&config, start - 1), None);
+    assert!(!game.running());
+    assert_eq!(game.client_round(&config, start - 60).starts_in, Some(60));
+    assert_eq!(
+        game.update_round(&config, start),
+        Some(RoundChange::Started)
+    );
+    assert!(game.running());
+
+    // Pause and resume in pause window
+    assert_eq!(
+        game.update_round(&config, start + 3600),
+        Some(RoundChange::Paused)
+    );
+    assert!(!game.running());
+    let replay = || state(&Game::replay(&config, &journal.events(), None));
+    assert_eq!(replay(), state(&game));
+    assert_eq!(
+        game.update_round(&config, start + 5400),
+        Some(RoundChange::Resumed)
+    );
+    assert!(game.running());
+
+    // Finish at end tick, with frozen scoreboard
+    game.ensure_user(&config, 1);
+    for _ in 0..3 {
+        game.tick_step(&config);
+    }
+    assert_eq!(game.round_ticks_left(&config), Some(0));
+    assert_eq!(
+        game.update_round(&config, start + 6000),
+        Some(RoundChange::Finished)
+    );
+    assert!(!game.running());
+    assert!(game.final_scoreboard().is_some());
+    assert_eq!(game.update_round(&config, start + 6000), None);
+    assert_eq!(replay(), state(&game));
+}
+
+/// Ensure the leaderboard ranks by configured score, with a breakdown.
+#[test]
+fn leaderboard_score() {
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let game = Game::default();
+    game.ensure_user(&config, 1);
+    game.ensure_user(&config, 2);
+
+    // User 1 liquidates an item, ranking first by money only
+    let cell = {
+        let users = game.users.read().unwrap();
+        let user = users[&1].read().unwrap();
+        let grid = &user.inventory.grid.items;
+        (0..grid.len())
+            .find(|i| matches!(&grid[*i], Some(item) if item.config.as_ref().unwrap().sell > 0))
+            .unwrap() as u8
+    };
+    game.user_sell(1, &config, cell).unwrap();
+    let leaderboard = game.leaderboard(&config);
+    assert_eq!(leaderboard[0].name, "Team 1");
+    assert_eq!(leaderboard[0].score, leaderboard[0].money as f64);
+    assert_eq!(leaderboard[0].breakdown.len(), 1);
+
+    // Held items are worth more than their sell value, user 2 ranks first
+    config.scoring.item_value = 2.0;
+    let leaderboard = game.leaderboard(&config);
+    assert_eq!(leaderboard[0].name, "Team 2");
+    let parts: Vec<_> = leaderboard[0].breakdown.iter().map(|p| &p.name).collect();
+    assert_eq!(parts, ["money", "item_value"]);
+    let total: f64 = leaderboard[0].breakdown.iter().map(|p| p.points).sum();
+    assert_eq!(leaderboard[0].score, total);
+}
+
+/// Ensure leaderboard history is snapshotted at the configured interval, and kept on save.
+#[test]
+fn leaderboard_history() {
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    config.game.history_interval_ticks = 5;
+    let game = Game::default();
+    game.reset_seeded(1);
+    game.ensure_user(&config, 1);
+    game.ensure_user(&config, 2);
+    for _ in 0..12 {
+        game.tick_step(&config);
+    }
+
+    let history = game.history(None);
+    let ticks: Vec<_> = history.iter().map(|s| s.tick).collect();
+    assert_eq!(ticks, [5, 10]);
+    let ids: Vec<_> = history[0].users.iter().map(|u| u.id).collect();
+    assert_eq!(ids, [1, 2]);
+    assert_eq!(game.history(Some(5)).len(), 1);
+
+    // History is saved with the game
+    let loaded: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(loaded.history(None), history);
+
+    // Reset clears history
+    game.reset_seeded(1);
+    assert!(game.history(None).is_empty());
+}
+
+/// Ensure the spectator leaderboard is limited and anonymized as configured.
+#[test]
+fn spectate_leaderboard() {
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let game = Game::default();
+    for id in 1..=3 {
+        game.ensure_user(&config, id);
+    }
+    assert_eq!(crate::ws::spectate::leaderboard(&config, &game).len(), 3);
+
+    config.spectate.top = Some(2);
+    config.spectate.hide_names = true;
+    let leaderboard = crate::ws::spectate::leaderboard(&config, &game);
+    let names: Vec<_> = leaderboard.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, ["#1", "#2"]);
+}
+
+/// Ensure admins can edit user inventories, and edits replay from the journal.
+#[test]
+fn admin_edit_inventory() {
+    use crate::game::admin::InventoryEdit;
+    use crate::types::ItemRef;
+
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let (game, journal) = game_with_journal("admin");
+    game.reset_seeded(1);
+
+    // Place and remove items, set balances
+    let edit = |edit| game.admin_edit_inventory(100, 1, &config, &edit);
+    let inventory = edit(InventoryEdit::SetItem {
+        cell: 63,
+        item: Some(ItemRef::from(10, 1)),
+    })
+    .unwrap();
+    assert!(inventory.grid.items[63].is_some());
+    let cell = first_item_cell(&game, 1);
+    let inventory = edit(InventoryEdit::SetItem { cell, item: None }).unwrap();
+    assert!(inventory.grid.items[cell as usize].is_none());
+    let inventory = edit(InventoryEdit::SetBalance {
+        money: Some(1234),
+        energy: None,
+    })
+    .unwrap();
+    assert_eq!(inventory.money, 1234);
+
+    // Reject invalid edits
+    assert!(edit(InventoryEdit::SetItem {
+        cell: 64,
+        item: None
+    })
+    .is_none());
+    assert!(edit(InventoryEdit::SetItem {
+        cell: 0,
+        item: Some(ItemRef::from(999, 0))
+    })
+    .is_none());
+    assert!(game
+        .admin_edit_inventory(100, 999, &config, &InventoryEdit::ClearQueue { cell: 0 })
+        .is_none());
+
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&game));
+}
+
+/// Ensure announcements are validated, kept for their targets, and replay from the journal.
+#[test]
+fn announcements() {
+    use crate::game::announce::{AnnouncementTarget, Severity};
+
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let (game, journal) = game_with_journal("announce");
+    game.reset_seeded(1);
+
+    let announce =
+        |target, text| game.announce(&config, 100, target, Severity::Warning, true, text);
+    let all = announce(AnnouncementTarget::All, " Lunch break ").unwrap();
+    assert_eq!(all.text, "Lunch break");
+    announce(AnnouncementTarget::User(1), "Team 1, come to the desk").unwrap();
+    announce(AnnouncementTarget::Admins, "Referees wanted").unwrap();
+    assert!(announce(AnnouncementTarget::All, " ").is_none());
+    assert!(announce(AnnouncementTarget::User(999), "Unknown").is_none());
+
+    // Users only receive announcements targeted at them
+    let ids = |user_id| -> Vec<u32> {
+        game.user_announcements(&config, user_id)
+            .iter()
+            .map(|a| a.id)
+            .collect()
+    };
+    assert_eq!(ids(1), [1, 2]);
+    assert_eq!(ids(2), [1]);
+    assert_eq!(ids(100), [1, 3]);
+
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&game));
+}
+
+/// Ensure admins can freeze, fine, drain and reward a single user, and sanctions replay.
+#[test]
+fn sanctions() {
+    use crate::game::sanction::Sanction;
+
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let (game, journal) = game_with_journal("sanction");
+    game.reset_seeded(1);
+    game.ensure_user(&config, 1);
+    let sanction = |sanction, reason| game.admin_sanction(100, 1, &config, &sanction, reason);
+
+    // Reason is required
+    assert!(sanction(Sanction::Unfreeze, " ").is_none());
+
+    // Frozen users can't act, their offers can't be accepted, their factories don't drop, until
+    // unfrozen
+    game.ensure_user(&config, 2);
+    let offer = ClientActionTradeOffer {
+        to: 2,
+        cells: vec![],
+        money: 1,
+        energy: 0,
+        request: vec![],
+    };
+    let (_, trade) = game.user_trade_offer(1, &config, &offer).unwrap();
+    let record = sanction(Sanction::Freeze { ticks: Some(3) }, "Cheating").unwrap();
+    assert_eq!(record.reason, "Cheating");
+    assert!(game.user_frozen(1));
+    assert!(game
+        .user_sell(1, &config, first_item_cell(&game, 1))
+        .is_none());
+    assert!(game.user_trade_accept(2, &config, trade.id).is_none());
+    let items = item_count(&game, 1);
+    for _ in 0..2 {
+        let (updates, _) = game.tick_step(&config);
+        assert!(updates[0].changed.is_empty());
+    }
+    assert_eq!(item_count(&game, 1), items);
+    game.tick_step(&config);
+    assert!(!game.user_frozen(1));
+    assert_eq!(game.user_trade_accept(2, &config, trade.id), Some(1));
+
+    // Fines, bonuses and energy drain
+    let before = money(&game, 1);
+    sanction(Sanction::Fine { money: 5 }, "Littering").unwrap();
+    sanction(
+        Sanction::Bonus {
+            money: 2,
+            energy: 10,
+        },
+        "Helping out",
+    )
+    .unwrap();
+    assert_eq!(money(&game, 1), before.saturating_sub(5) + 2);
+    let energy = |game: &Game| game.user_client_inventory(&config, 1).unwrap().energy;
+    let before = energy(&game);
+    sanction(
+        Sanction::Drain {
+            energy: 1,
+            ticks: 2,
+        },
+        "Late",
+    )
+    .unwrap();
+    for _ in 0..4 {
+        game.tick_step(&config);
+    }
+    assert_eq!(energy(&game), before.saturating_sub(2));
+
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&game));
+}
+
+/// Ensure configured outposts reward, cool down, open and get disabled as configured, and replay.
+#[test]
+fn outposts() {
+    use crate::config::{ConfigOutpost, ConfigWindow};
+    use crate::game::code;
+    use crate::types::{Amount, ItemRef};
+
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let item = Amount::Item {
+        item: ItemRef::from(10, 1),
+        quantity: 1,
+    };
+    // Without listed outposts, any ID is only known if explicitly allowed
+    assert!(config.outposts.known(3));
+    config.outposts.allow_unlisted = false;
+    assert!(!config.outposts.known(3));
+
+    config.outposts.list = vec![
+        ConfigOutpost {
+            reward: vec![item],
+            cooldown_ticks: Some(2),
+            ..super::outpost(1)
+        },
+        ConfigOutpost {
+            reward: vec![Amount::Money { money: 100 }],
+            bonus: true,
+            ..super::outpost(2)
+        },
+    ];
+    config.validate().expect("outposts config is invalid");
+
+    let (game, journal) = game_with_journal("outpost");
+    game.reset_seeded(1);
+    game.ensure_user(&config, 1);
+
+    // Unknown outposts are rejected
+    assert!(code::validate_outpost_token(&config, &code::get_outpost_token(&config, 3)).is_none());
+    assert_eq!(
+        code::validate_outpost_token(&config, &code::get_outpost_token(&config, 1))
+            .map(|token| token.outpost),
+        Some(1)
+    );
+    assert!(game.user_scan_code(1, &config, Some(3)).is_none());
+
+    // Item only outpost, scannable again after its cooldown
+    let (before_money, before_items) = (money(&game, 1), item_count(&game, 1));
+    assert!(game.user_scan_code(1, &config, Some(1)).is_some());
+    assert_eq!(money(&game, 1), before_money);
+    assert_eq!(item_count(&game, 1), before_items + 1);
+    assert!(game.user_scan_code(1, &config, Some(1)).is_none());
+    game.tick_step(&config);
+    game.tick_step(&config);
+    assert!(game.user_scan_code(1, &config, Some(1)).is_some());
+
+    // Bonus outpost adds its reward on top of the default reward
+    let before = money(&game, 1);
+    assert!(game.user_scan_code(1, &config, Some(2)).is_some());
+    assert!(money(&game, 1) >= before + 100 + config.outposts.money_min);
+
+    // Disabled outposts can't be scanned, mock scans pick enabled outposts
+    game.admin_set_outpost_enabled(100, &config, 2, false)
+        .unwrap();
+    assert!(!game.outpost_enabled(&config, 2));
+    assert!(game.user_scan_code(1, &config, Some(2)).is_none());
+    assert!(game
+        .admin_set_outpost_enabled(100, &config, 3, false)
+        .is_none());
+    game.tick_step(&config);
+    game.tick_step(&config);
+    assert!(game.user_scan_code(1, &config, None).is_some());
+    assert_eq!(
+        game.users.read().unwrap()[&1]
+            .read()
+            .unwrap()
+            .outposts
+            .back(),
+        Some(&1)
+    );
+
+    // Outposts are closed outside their opening hours
+    config.outposts.list[0].hours = vec![ConfigWindow {
+        start: "2021-06-12T10:00:00Z".into(),
+        end: "2021-06-12T12:00:00Z".into(),
+    }];
+    assert!(!config.outposts.list[0].open_at(crate::auth::now()));
+    assert!(config.outposts.list[1].open_at(crate::auth::now()));
+
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&game));
+}
+
+/// Ensure outposts are captured and stolen following their contest rules, and pay income.
+#[test]
+fn outpost_capture() {
+    use crate::config::ConfigOutpost;
+    use crate::types::Amount;
+
+    let mut config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    config.outposts.list = vec![ConfigOutpost {
+        name: "Hill".into(),
+        reward: vec![Amount::Energy { energy: 1 }],
+        cooldown_ticks: Some(0),
+        capture: true,
+        steal_cooldown_ticks: Some(3),
+        income: vec![Amount::Money { money: 5 }],
+        income_interval_ticks: 2,
+        ..super::outpost(1)
+    }];
+    config
+        .validate()
+        .expect("outpost capture config is invalid");
+
+    let (game, journal) = game_with_journal("capture");
+    game.reset_seeded(1);
+    game.ensure_user(&config, 1);
+    game.ensure_user(&config, 2);
+
+    // Unclaimed outpost is captured by the first scan
+    let (_, _, capture) = game.user_scan_code(1, &config, Some(1)).unwrap();
+    let capture = capture.expect("outpost not captured");
+    assert_eq!((capture.outpost, capture.previous), (1, None));
+    assert_eq!(game.outpost_owner(1).map(|owner| owner.user), Some(1));
+    assert_eq!(game.client_outpost_owners(&config).len(), 1);
+
+    // Owner earns income every income interval
+    let (money_1, money_2) = (money(&game, 1), money(&game, 2));
+    game.tick_step(&config);
+    assert_eq!(money(&game, 1), money_1);
+    game.tick_step(&config);
+    assert_eq!(money(&game, 1), money_1 + 5);
+    assert_eq!(money(&game, 2), money_2);
+
+    // Other user can't steal the outpost until the steal cooldown passed
+    let (_, _, capture) = game.user_scan_code(2, &config, Some(1)).unwrap();
+    assert!(capture.is_none());
+    game.tick_step(&config);
+    let (_, _, capture) = game.user_scan_code(2, &config, Some(1)).unwrap();
+    assert_eq!(capture.map(|c| c.previous), Some(Some(1)));
+    assert_eq!(game.outpost_owner(1).map(|owner| owner.user), Some(2));
+
+    // Income goes to the new owner, disabled outposts don't pay
+    let money_1 = money(&game, 1);
+    game.tick_step(&config);
+    assert_eq!(money(&game, 1), money_1);
+    assert_eq!(money(&game, 2), money_2 + 5);
+    game.admin_set_outpost_enabled(100, &config, 1, false)
+        .unwrap();
+    game.tick_step(&config);
+    game.tick_step(&config);
+    assert_eq!(money(&game, 2), money_2 + 5);
+
+    let events = journal.events();
+    assert_eq!(state(&Game::replay(&config, &events, None)), state(&game));
+
+    // Reset drops all owners
+    game.reset_seeded(1);
+    assert!(game.outpost_owner(1).is_none());
+}
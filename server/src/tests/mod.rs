@@ -1 +1,15 @@
+pub mod audit;
+pub mod auth;
+pub mod code;
 pub mod config;
+pub mod game;
+pub mod save;
+pub mod sim;
+
+use crate::config::ConfigOutpost;
+
+/// Outpost configuration with the given ID, defaults as if configured with only an ID and name.
+fn outpost(id: u32) -> ConfigOutpost {
+    toml::from_str(&format!("id = {0}\nname = \"Outpost {0}\"", id))
+        .expect("failed to parse outpost config")
+}
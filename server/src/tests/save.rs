@@ -0,0 +1,31 @@
+use std::fs;
+
+use crate::save;
+
+/// Ensure corrupt saves fall back to the newest valid backup.
+#[test]
+fn load_backup_fallback() {
+    let dir = std::env::temp_dir().join(format!("mms-test-save-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("save.json");
+    let parse = |data: &[u8]| serde_json::from_slice::<u32>(data).map_err(|e| e.to_string());
+
+    // Missing save file is not an error
+    assert_eq!(save::load(&path, parse), Ok(None));
+
+    // Keep rotating backups
+    for i in 0..4u32 {
+        save::write_atomic(&path, i.to_string().as_bytes()).unwrap();
+        save::backup(&path, 2).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    assert_eq!(save::backups(&path).len(), 2);
+    assert_eq!(save::load(&path, parse), Ok(Some(3)));
+
+    // Fall back to newest valid backup
+    fs::write(&path, b"{\"trunc").unwrap();
+    fs::write(&save::backups(&path)[0], b"").unwrap();
+    assert_eq!(save::load(&path, parse), Ok(Some(2)));
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,33 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::config;
+use crate::sim::{self, Options, Strategy};
+
+/// Ensure simulations are reproducible, and bots make progress.
+#[test]
+fn simulate() {
+    let config =
+        config::load(Path::new(crate::CONFIG_PATH)).expect("failed to load config from file");
+    let options = Options {
+        teams: 3,
+        ticks: 600,
+        action_interval: 5,
+        scan_interval: 100,
+        strategy: vec![Strategy::Merger, Strategy::Seller, Strategy::Buyer],
+        sample_interval: 100,
+        seed: Some(1),
+    };
+
+    let report = sim::run(&config, &options);
+    let again = sim::run(&config, &options);
+    assert_eq!(
+        serde_json::to_value(&report).unwrap(),
+        serde_json::to_value(&again).unwrap()
+    );
+
+    assert_eq!(report.teams.len(), 3);
+    assert_eq!(report.samples.len(), 7);
+    let defaults: HashSet<_> = config.defaults.inventory.iter().collect();
+    assert!(report.teams.iter().all(|t| t.discovered > defaults.len()));
+}
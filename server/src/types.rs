@@ -1,3 +1,5 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 /// An amount of money or items.
@@ -15,6 +17,16 @@ pub enum Amount {
     Item { item: ItemRef, quantity: u8 },
 }
 
+impl Amount {
+    /// Number of inventory cells this amount occupies.
+    pub fn cells(&self) -> usize {
+        match self {
+            Amount::Item { quantity, .. } => *quantity as usize,
+            _ => 0,
+        }
+    }
+}
+
 /// Item reference.
 // TODO: don't use string, use integer properties instead
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
@@ -41,3 +53,9 @@ impl ItemRef {
         self.0.split_once('.')?.1.parse().ok()
     }
 }
+
+impl fmt::Display for ItemRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
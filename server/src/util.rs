@@ -16,6 +16,15 @@ pub fn i_to_xy(i: u8) -> (u32, u32) {
     )
 }
 
+/// Escape a CSV field, quoting it if needed.
+pub fn csv_escape(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
 /// Produces `1.0`.
 pub const fn one() -> f64 {
     1.0
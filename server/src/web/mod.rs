@@ -1,16 +1,18 @@
 //! Webserver module.
 
+use std::net::SocketAddr;
+
 use warp::http::StatusCode;
 use warp::Reply;
 
 use crate::routes::ErrorMessage;
-use crate::state::SharedState;
+use crate::state::SharedServer;
 
 /// HTTP server.
-pub async fn server(state: SharedState) {
+pub async fn server(server: SharedServer, addr: SocketAddr) {
     info!("Initialzing server...");
-    let routes = crate::routes::routes(state);
-    warp::serve(routes).run(crate::HOST).await;
+    let routes = crate::routes::routes(server);
+    warp::serve(routes).run(addr).await;
 }
 
 /// A custom and easily returnable API error.
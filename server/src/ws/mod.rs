@@ -1,3 +1,5 @@
+pub mod spectate;
+
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
@@ -5,21 +7,28 @@ use std::sync::{
 
 use futures::stream::SplitStream;
 use futures::{SinkExt, StreamExt, TryFutureExt};
-use rand::Rng;
+use serde_json::{json, Value};
 use tokio::sync::{mpsc, RwLock};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use warp::ws::{Message, WebSocket};
 use warp::Filter;
 
+use crate::audit::AuditQuery;
 use crate::auth::{generate_client_id, Client, Session};
 use crate::client::{
-    ClientActionBuy, ClientActionMerge, ClientActionRewardUser, ClientActionSell, ClientActionSwap,
-    ClientInventory, ClientSession, MsgRecv, MsgRecvKind, MsgSend, MsgSendKind,
+    ClientActionAnnounce, ClientActionBuy, ClientActionEditInventory, ClientActionMerge,
+    ClientActionRewardUser, ClientActionSanction, ClientActionSell, ClientActionSetOutpostEnabled,
+    ClientActionSwap, ClientActionTradeOffer, ClientInventory, ClientSession, ClientUserSession,
+    MsgRecv, MsgRecvKind, MsgSend, MsgSendKind,
 };
-use crate::state::SharedState;
+use crate::config::Permission;
+use crate::game::admin::InventoryEdit;
+use crate::game::announce::AnnouncementTarget;
+use crate::game::outpost::OutpostCapture;
+use crate::state::{SharedServer, SharedState};
 
 /// New client connected.
-pub async fn connected(state: SharedState, ws: WebSocket) {
+pub async fn connected(server: SharedServer, ws: WebSocket) {
     // Obtain unique client ID
     let client_id = generate_client_id();
     info!("WS({}): connect", client_id);
@@ -27,14 +36,14 @@ pub async fn connected(state: SharedState, ws: WebSocket) {
     // Split socket sender/receiver, use unbound channel for buffering/flushing
     let (mut user_ws_tx, mut user_ws_rx) = ws.split();
 
-    // Handle auth handshake, attempt to gracefully close otherwise
-    let session = match handle_auth(state.clone(), client_id, &mut user_ws_rx).await {
-        Some(session) => session,
+    // Handle auth handshake, find session game, attempt to gracefully close otherwise
+    let session = handle_auth(&server, client_id, &mut user_ws_rx).await;
+    let (session, state) = match session.and_then(|s| Some((server.game(&s.game)?.clone(), s))) {
+        Some((state, session)) => (session, state),
         None => {
             if let Ok(ws) = user_ws_tx.reunite(user_ws_rx) {
                 ws.close();
             }
-            disconnected(state, client_id);
             return;
         }
     };
@@ -56,15 +65,14 @@ pub async fn connected(state: SharedState, ws: WebSocket) {
     });
 
     // Register client for a user
-    state
-        .clients
-        .register(Client::new(client_id, session.user_id, tx));
+    server.sessions.touch(session.token());
+    state.clients.register(Client::new(client_id, &session, tx));
 
     // Send game state to client
     send_initial(state.clone(), client_id, &session).await;
 
     // Handle client messages
-    handle(state.clone(), client_id, &mut user_ws_rx).await;
+    handle(&server, state.clone(), client_id, &mut user_ws_rx).await;
 
     // Socket disconnected when this is reached
     disconnected(state, client_id).await;
@@ -74,7 +82,7 @@ pub async fn connected(state: SharedState, ws: WebSocket) {
 ///
 /// Returns session on success, `None` on failure after which the socket should be closed.
 async fn handle_auth(
-    state: SharedState,
+    server: &SharedServer,
     client_id: usize,
     rx: &mut SplitStream<WebSocket>,
 ) -> Option<Session> {
@@ -110,11 +118,12 @@ async fn handle_auth(
 
         // Validate session
         let token = &session.token;
-        let session = state.sessions.get_valid(token);
+        let session = server.sessions.get(token);
         if let Some(session) = &session {
             info!(
-                "WS({}): auth success (user: {}, token: {}...)",
+                "WS({}): auth success (game: {}, user: {}, token: {}...)",
                 client_id,
+                session.game,
                 session.user_id,
                 &token[0..16]
             );
@@ -133,9 +142,18 @@ async fn handle_auth(
 
 /// Send current state to client.
 async fn send_initial(state: SharedState, client_id: usize, session: &Session) {
-    // Send game state
+    // Send game and round state
     let msg = MsgSendKind::GameState(state.game.running());
     send_to_client(&state, client_id, &msg.into());
+    send_round(&state, Some(client_id));
+
+    // Send announcements for user
+    let msg = MsgSendKind::Announcements(
+        state
+            .game
+            .user_announcements(&state.config, session.user_id),
+    );
+    send_to_client(&state, client_id, &msg.into());
 
     // Send session state
     let session = match ClientSession::from_session(&state.config, session) {
@@ -150,10 +168,21 @@ async fn send_initial(state: SharedState, client_id: usize, session: &Session) {
 }
 
 /// Handle client messages.
-async fn handle(state: SharedState, client_id: usize, user_ws_rx: &mut SplitStream<WebSocket>) {
+async fn handle(
+    server: &SharedServer,
+    state: SharedState,
+    client_id: usize,
+    user_ws_rx: &mut SplitStream<WebSocket>,
+) {
     // TODO: timeout if not recieving heartbeat each minute
 
     while let Some(result) = user_ws_rx.next().await {
+        // Stop handling if client was unregistered, such as when its session is revoked
+        if !state.clients.is_registered(client_id) {
+            info!("WS({}): client unregistered, stop handling", client_id);
+            break;
+        }
+
         // Parse message
         let msg = match result {
             Ok(msg) => msg,
@@ -184,12 +213,12 @@ async fn handle(state: SharedState, client_id: usize, user_ws_rx: &mut SplitStre
             }
         };
 
-        handle_msg(&state, client_id, msg).await;
+        handle_msg(server, &state, client_id, msg).await;
     }
 }
 
 /// Handle client messages.
-async fn handle_msg(state: &SharedState, client_id: usize, msg: MsgRecv) {
+async fn handle_msg(server: &SharedServer, state: &SharedState, client_id: usize, msg: MsgRecv) {
     // Report error kinds
     let msg = match msg {
         MsgRecv::Ok(msg) => msg,
@@ -202,6 +231,20 @@ async fn handle_msg(state: &SharedState, client_id: usize, msg: MsgRecv) {
         }
     };
 
+    // Frozen users can't act
+    if msg.is_user_action() {
+        let frozen = state
+            .clients
+            .client_user_id(client_id)
+            .map(|user_id| state.game.user_frozen(user_id))
+            .unwrap_or(false);
+        if frozen {
+            let msg = MsgSendKind::Toast(crate::lang::USER_FROZEN.into());
+            send_to_client(state, client_id, &msg.into());
+            return;
+        }
+    }
+
     // Handle specific message
     match msg {
         MsgRecvKind::GetGame => get_game(state, client_id),
@@ -218,15 +261,40 @@ async fn handle_msg(state: &SharedState, client_id: usize, msg: MsgRecv) {
         MsgRecvKind::GetLeaderboard => get_leaderboard(state, client_id),
         MsgRecvKind::GetOutpostToken(id) => get_outpost_token(state, client_id, id),
         MsgRecvKind::ActionRewardUser(action) => action_reward_user(state, client_id, action),
+        MsgRecvKind::GetOutposts => get_outposts(state, client_id),
+        MsgRecvKind::ActionSetOutpostEnabled(action) => {
+            action_set_outpost_enabled(state, client_id, action)
+        }
+        MsgRecvKind::GetOutpostOwners => get_outpost_owners(state, client_id),
+        MsgRecvKind::GetTrades => get_trades(state, client_id),
+        MsgRecvKind::ActionTradeOffer(action) => action_trade_offer(state, client_id, action),
+        MsgRecvKind::ActionTradeAccept(id) => action_trade_accept(state, client_id, id),
+        MsgRecvKind::ActionTradeDecline(id) => action_trade_decline(state, client_id, id),
+        MsgRecvKind::GetMissions => get_missions(state, client_id),
+        MsgRecvKind::ActionSubmitMission(id) => action_submit_mission(state, client_id, id),
+        MsgRecvKind::GetSessions(user_id) => get_sessions(server, state, client_id, user_id),
+        MsgRecvKind::ActionRevokeSession(id) => {
+            action_revoke_session(server, state, client_id, Some(&id), None)
+        }
+        MsgRecvKind::ActionRevokeUserSessions(user_id) => {
+            action_revoke_session(server, state, client_id, None, Some(user_id))
+        }
+        MsgRecvKind::GetHistory(since) => get_history(state, client_id, since),
+        MsgRecvKind::GetUserInventory(user_id) => get_user_inventory(state, client_id, user_id),
+        MsgRecvKind::ActionEditInventory(action) => action_edit_inventory(state, client_id, action),
+        MsgRecvKind::ActionAnnounce(action) => action_announce(state, client_id, action),
+        MsgRecvKind::ActionSanction(action) => action_sanction(state, client_id, action),
+        MsgRecvKind::GetAudit(query) => get_audit(state, client_id, query),
     }
 }
 
 fn get_game(state: &SharedState, client_id: usize) {
     debug!("Client {} invoked get game", client_id);
 
-    // Send game state
+    // Send game and round state
     let msg = MsgSendKind::GameState(state.game.running());
     send_to_client(state, client_id, &msg.into());
+    send_round(state, Some(client_id));
 
     // Find client user ID
     let user_id = match state.clients.client_user_id(client_id) {
@@ -234,75 +302,74 @@ fn get_game(state: &SharedState, client_id: usize) {
         None => return,
     };
 
-    // Check if user has user role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-
-    if role_game {
+    // Check if user plays the game
+    if state.config.user_can(user_id, Permission::Play) {
         // Send item configuration
         let msg = MsgSendKind::ConfigItems(state.config.items.clone());
         send_to_client(state, client_id, &msg.into());
 
-        // Also send inventory state
+        // Also send inventory state and missions
         get_inventory(state, client_id);
+        get_missions(state, client_id);
     }
 }
 
 fn set_game_running(state: &SharedState, client_id: usize, running: bool) {
     debug!("Client {} invoked set game running: {}", client_id, running);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "change game state") {
         Some(id) => id,
         None => return,
     };
 
-    // User must be admin
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_admin)
-        .unwrap_or(false);
-    if !role_admin {
-        warn!("Non-admin user tried to change game state");
+    // Finished rounds must be reset to play again
+    if running && state.game.round_finished() {
+        let msg = MsgSendKind::Toast(crate::lang::ROUND_FINISHED.into());
+        send_to_client(state, client_id, &msg.into());
         return;
     }
 
     // Set running state
+    let before = state.game.running();
     state.game.set_running(running);
+    state.audit(
+        user_id,
+        "set_game_running",
+        None,
+        json!(before),
+        json!(running),
+    );
 
-    // Send game state to all clients
+    // Send game and round state to all clients
     let msg = MsgSendKind::GameState(running);
     send_to_all(state, Some(client_id), &msg.into());
+    send_round(state, None);
 }
 
 fn reset_game(state: &SharedState, client_id: usize) {
     debug!("Client {} invoked game reset", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "reset game") {
         Some(id) => id,
         None => return,
     };
 
-    // User must be admin
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_admin)
-        .unwrap_or(false);
-    if !role_admin {
-        warn!("Non-admin user tried to reset game");
-        return;
-    }
-
     // Reset game
-    state.game.reset();
+    let before = json!({"tick": state.game.tick(), "seed": state.game.seed()});
+    state.game.reset(&state.config);
+    state.audit(
+        user_id,
+        "reset_game",
+        None,
+        before,
+        json!({"seed": state.game.seed()}),
+    );
 
     info!("Game is reset by admin");
+    send_round(state, None);
+    send_outpost_owners(state, None);
 
     // Update each client
     for client_id in state.clients.client_ids() {
@@ -324,23 +391,12 @@ fn reset_game(state: &SharedState, client_id: usize) {
 fn get_inventory(state: &SharedState, client_id: usize) {
     debug!("Client {} invoked get inventory", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "get inventory") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to get inventory");
-        return;
-    }
-
     // Get inventory
     let mut inventory = match state.game.user_client_inventory(&state.config, user_id) {
         Some(inv) => inv,
@@ -363,28 +419,23 @@ fn get_stats(state: &SharedState, client_id: usize, team_id: Option<u32>) {
 
     // Handle custom team stats request
     if let Some(other_id) = team_id {
-        // To get stats for another user, user must have admin role
-        if target_id != other_id {
-            let role_game = state
-                .config
-                .user(target_id)
-                .map(|u| u.role_admin)
-                .unwrap_or(false);
-            if !role_game {
-                warn!("Non-admin tried to get stats for other user");
-                return;
-            }
+        // To get stats for another user, user must be referee
+        if target_id != other_id
+            && authorize(
+                state,
+                client_id,
+                Permission::Referee,
+                "get stats for other user",
+            )
+            .is_none()
+        {
+            return;
         }
         target_id = other_id;
     }
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(target_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
+    // Target user must play the game
+    if !state.config.user_can(target_id, Permission::Play) {
         warn!("Non-game user tried to get stats");
         let msg = MsgSendKind::Stats(None);
         send_to_client(state, client_id, &msg.into());
@@ -405,23 +456,12 @@ fn get_stats(state: &SharedState, client_id: usize, team_id: Option<u32>) {
 fn action_swap(state: &SharedState, client_id: usize, action: ClientActionSwap) {
     debug!("Client {} invoked swap action", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "swap items") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to swap items");
-        return;
-    }
-
     // Do swap, get inventory
     let mut inventory =
         match state
@@ -440,23 +480,12 @@ fn action_swap(state: &SharedState, client_id: usize, action: ClientActionSwap)
 fn action_merge(state: &SharedState, client_id: usize, action: ClientActionMerge) {
     debug!("Client {} invoked merge action", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "merge items") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to merge items");
-        return;
-    }
-
     // Fetch cell item that we'll be merging, get config, return if null
     let cell_item = match state
         .game
@@ -531,23 +560,12 @@ fn action_merge(state: &SharedState, client_id: usize, action: ClientActionMerge
 fn action_buy(state: &SharedState, client_id: usize, action: ClientActionBuy) {
     debug!("Client {} invoked buy action", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "buy item") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to buy item");
-        return;
-    }
-
     // Resolve item from config
     let item = match state.config.item(&action.item) {
         Some(item) => item,
@@ -613,23 +631,12 @@ fn action_buy(state: &SharedState, client_id: usize, action: ClientActionBuy) {
 fn action_sell(state: &SharedState, client_id: usize, action: ClientActionSell) {
     debug!("Client {} invoked sell action", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "sell item") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have game role
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to sell item");
-        return;
-    }
-
     // Do sell, get inventory
     let mut inventory = match state.game.user_sell(user_id, &state.config, action.cell) {
         Some(inv) => inv,
@@ -653,29 +660,14 @@ fn action_sell(state: &SharedState, client_id: usize, action: ClientActionSell)
 fn action_scan_code(state: &SharedState, client_id: usize, token: Option<String>) {
     debug!("Client {} invoked scan code action", client_id);
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must play the game, and be admin to mock scans
+    let user_id = match authorize(state, client_id, Permission::Play, "scan code") {
         Some(id) => id,
         None => return,
     };
-
-    // User must have correct roles
-    let role_game = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_game)
-        .unwrap_or(false);
-    if !role_game {
-        warn!("Non-game user tried to scan code");
-        return;
-    }
-    if token.is_none() && !role_admin {
-        warn!("Non-admin user tried to mock a code scan");
+    if token.is_none()
+        && authorize(state, client_id, Permission::Admin, "mock a code scan").is_none()
+    {
         return;
     }
 
@@ -687,42 +679,74 @@ fn action_scan_code(state: &SharedState, client_id: usize, token: Option<String>
         return;
     }
 
-    // Validate token and get outpost ID
-    let outpost_id = if let Some(token) = token {
-        crate::game::code::validate_outpost_token(&state.config, &token)
-    } else {
-        Some(rand::thread_rng().gen_range(1..=10))
+    // Validate token and get outpost ID, a random outpost is picked for mock scans
+    let token = match token {
+        Some(token) => match crate::game::code::validate_outpost_token(&state.config, &token) {
+            Some(token) => Some(token),
+            None => {
+                warn!("User scanned invalid code");
+                let msg = MsgSendKind::CodeResult(false);
+                send_to_client(state, client_id, &msg.into());
+                return;
+            }
+        },
+        None => None,
     };
-    if outpost_id.is_none() {
-        warn!("User scanned invalid code");
+    let outpost_id = token.as_ref().map(|token| token.outpost);
+
+    // Outpost must be within its opening hours
+    if outpost_id
+        .map(|id| outpost_closed(state, id))
+        .unwrap_or(false)
+    {
+        warn!("User scanned code of closed outpost");
         let msg = MsgSendKind::CodeResult(false);
         send_to_client(state, client_id, &msg.into());
+        let msg = MsgSendKind::Toast(crate::lang::OUTPOST_CLOSED.into());
+        send_to_client(state, client_id, &msg.into());
         return;
     }
 
-    // Run scan code action
-    let inventory = match state
-        .game
-        .user_scan_code(user_id, &state.config, outpost_id.unwrap())
-    {
-        Some(inventory) => inventory,
-        None => {
-            warn!("User scanned same post as last time");
+    // Single-use tokens can only be redeemed once, released again if the scan fails
+    if let Some(token) = &token {
+        if state.config.outposts.single_use
+            && !state.tokens.redeem(user_id, token, crate::auth::now())
+        {
+            warn!("User scanned already redeemed code");
             let msg = MsgSendKind::CodeResult(false);
             send_to_client(state, client_id, &msg.into());
             return;
         }
-    };
+    }
+
+    // Run scan code action
+    let (inventory, _, capture) =
+        match state
+            .game
+            .user_scan_code(user_id, &state.config, outpost_id)
+        {
+            Some(result) => result,
+            None => {
+                warn!("User scanned disabled, cooling down or same post as last time");
+                if let Some(token) = &token {
+                    state.tokens.release(user_id, token);
+                }
+                let msg = MsgSendKind::CodeResult(false);
+                send_to_client(state, client_id, &msg.into());
+                return;
+            }
+        };
 
     let msg = MsgSendKind::CodeResult(true);
     send_to_client(state, client_id, &msg.into());
 
-    // Send user balances update
-    let msg = MsgSendKind::InventoryBalances {
-        money: inventory.money,
-        energy: inventory.energy,
-    };
+    // Send new inventory state, outposts may reward items
+    let msg = MsgSendKind::Inventory(inventory);
     send_to_user(state, Some(client_id), user_id, &msg.into());
+
+    if let Some(capture) = capture {
+        send_outpost_capture(state, Some(client_id), user_id, capture);
+    }
 }
 
 fn get_leaderboard(state: &SharedState, client_id: usize) {
@@ -732,25 +756,221 @@ fn get_leaderboard(state: &SharedState, client_id: usize) {
     let msg = MsgSendKind::GameState(state.game.running());
     send_to_client(state, client_id, &msg.into());
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must be referee
+    let user_id = match authorize(state, client_id, Permission::Referee, "get leaderboard") {
         Some(id) => id,
         None => return,
     };
 
-    // User must have admin role
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_admin)
-        .unwrap_or(false);
-    if !role_admin {
-        warn!("Non-game user tried to get leaderboard");
+    // Get leaderboard, frozen when the round finished, send to client
+    let leaderboard = state
+        .game
+        .final_scoreboard()
+        .unwrap_or_else(|| state.game.leaderboard(&state.config));
+    let msg = MsgSendKind::Leaderboard(leaderboard);
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn get_history(state: &SharedState, client_id: usize, since: Option<u64>) {
+    debug!("Client {} invoked get history", client_id);
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "get history") {
+        Some(id) => id,
+        None => return,
+    };
+
+    let msg = MsgSendKind::History(state.game.history(since));
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn get_user_inventory(state: &SharedState, client_id: usize, target_id: u32) {
+    debug!(
+        "Client {} invoked get inventory of user {}",
+        client_id, target_id
+    );
+
+    // User must be referee
+    if authorize(
+        state,
+        client_id,
+        Permission::Referee,
+        "get inventory of other user",
+    )
+    .is_none()
+    {
         return;
     }
 
-    // Get leaderboard, send to client
-    let msg = MsgSendKind::Leaderboard(state.game.leaderboard());
+    // Target must be a game user
+    if !state.config.user_can(target_id, Permission::Play) {
+        return;
+    }
+
+    // Get inventory, send to admin
+    state.game.ensure_user(&state.config, target_id);
+    let inventory = match state.game.user_client_inventory(&state.config, target_id) {
+        Some(inv) => inv,
+        None => return,
+    };
+    let msg = MsgSendKind::UserInventory {
+        user_id: target_id,
+        inventory,
+    };
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn action_edit_inventory(state: &SharedState, client_id: usize, action: ClientActionEditInventory) {
+    debug!(
+        "Client {} invoked edit inventory of user {}",
+        client_id, action.user_id
+    );
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "edit inventory") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Edit inventory
+    let cell = match action.edit {
+        InventoryEdit::SetItem { cell, .. } | InventoryEdit::ClearQueue { cell } => Some(cell),
+        InventoryEdit::SetBalance { .. } => None,
+    };
+    let before = audit_inventory(state, action.user_id, cell);
+    let inventory =
+        match state
+            .game
+            .admin_edit_inventory(user_id, action.user_id, &state.config, &action.edit)
+        {
+            Some(inventory) => inventory,
+            None => {
+                let msg = MsgSendKind::Toast(crate::lang::INVENTORY_EDIT_INVALID.into());
+                send_to_client(state, client_id, &msg.into());
+                return;
+            }
+        };
+    info!(
+        "Admin {} edited inventory of user {}: {:?}",
+        user_id, action.user_id, action.edit
+    );
+    state.audit(
+        user_id,
+        "edit_inventory",
+        Some(action.user_id),
+        before,
+        audit_inventory(state, action.user_id, cell),
+    );
+
+    // Push inventory to user, send new state and confirmation to admin
+    let msg = MsgSendKind::Inventory(inventory);
+    send_to_user(state, Some(client_id), action.user_id, &msg.into());
+    get_user_inventory(state, client_id, action.user_id);
+    let msg = MsgSendKind::Toast(crate::lang::INVENTORY_EDITED.into());
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn action_announce(state: &SharedState, client_id: usize, action: ClientActionAnnounce) {
+    debug!("Client {} invoked announce", client_id);
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "announce") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Make announcement
+    let announcement = match state.game.announce(
+        &state.config,
+        user_id,
+        action.target,
+        action.severity,
+        action.persistent,
+        &action.text,
+    ) {
+        Some(announcement) => announcement,
+        None => {
+            let msg = MsgSendKind::Toast(crate::lang::ANNOUNCEMENT_INVALID.into());
+            send_to_client(state, client_id, &msg.into());
+            return;
+        }
+    };
+    info!(
+        "Admin {} announced to {:?}: {}",
+        user_id, announcement.target, announcement.text
+    );
+    let target = match announcement.target {
+        AnnouncementTarget::User(id) => Some(id),
+        _ => None,
+    };
+    state.audit(
+        user_id,
+        "announce",
+        target,
+        Value::Null,
+        json!(announcement),
+    );
+
+    // Send to all targeted clients
+    let target = announcement.target;
+    let msg = MsgSendKind::Announcement(announcement);
+    send_to_all_where(state, Some(client_id), &msg.into(), |user_id| {
+        target.includes(&state.config, user_id)
+    });
+}
+
+fn action_sanction(state: &SharedState, client_id: usize, action: ClientActionSanction) {
+    debug!(
+        "Client {} invoked sanction of user {}",
+        client_id, action.user_id
+    );
+
+    // Find client user ID, user must be referee
+    let user_id = match authorize(state, client_id, Permission::Referee, "apply sanction") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Apply sanction
+    let before = audit_inventory(state, action.user_id, None);
+    let record = match state.game.admin_sanction(
+        user_id,
+        action.user_id,
+        &state.config,
+        &action.sanction,
+        &action.reason,
+    ) {
+        Some(record) => record,
+        None => {
+            let msg = MsgSendKind::Toast(crate::lang::SANCTION_INVALID.into());
+            send_to_client(state, client_id, &msg.into());
+            return;
+        }
+    };
+    info!(
+        "Admin {} applied {:?} to user {}, reason: {}",
+        user_id, record.sanction, action.user_id, record.reason
+    );
+    let mut after = audit_inventory(state, action.user_id, None);
+    after["sanction"] = json!(record.sanction);
+    after["reason"] = json!(record.reason);
+    state.audit(user_id, "sanction", Some(action.user_id), before, after);
+
+    // Notify user of sanction and new balances
+    let msg = MsgSendKind::Sanction(record);
+    send_to_user(state, Some(client_id), action.user_id, &msg.into());
+    if let Some(inventory) = state
+        .game
+        .user_client_inventory(&state.config, action.user_id)
+    {
+        let msg = MsgSendKind::InventoryBalances {
+            money: inventory.money,
+            energy: inventory.energy,
+        };
+        send_to_user(state, Some(client_id), action.user_id, &msg.into());
+    }
+
+    let msg = MsgSendKind::Toast(crate::lang::SANCTION_APPLIED.into());
     send_to_client(state, client_id, &msg.into());
 }
 
@@ -760,28 +980,30 @@ fn get_outpost_token(state: &SharedState, client_id: usize, outpost_id: u32) {
         client_id, outpost_id
     );
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must be allowed to show outpost code
+    let user_id = match authorize(
+        state,
+        client_id,
+        Permission::Outpost(outpost_id),
+        "get outpost token",
+    ) {
         Some(id) => id,
         None => return,
     };
 
-    // User must have admin role
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_admin)
-        .unwrap_or(false);
-    if !role_admin {
-        warn!("Non-game user tried to get outpost token");
-        return;
-    }
+    state.audit(
+        user_id,
+        "get_outpost_token",
+        None,
+        Value::Null,
+        json!({ "outpost": outpost_id }),
+    );
 
     // Generate outpost token and send it back
-    let msg = MsgSendKind::OutpostToken(crate::game::code::get_outpost_token(
-        &state.config,
-        outpost_id,
-    ));
+    let msg = MsgSendKind::OutpostToken {
+        token: crate::game::code::get_outpost_token(&state.config, outpost_id),
+        interval_secs: state.config.outposts.token_interval_secs,
+    };
     send_to_client(state, client_id, &msg.into());
 }
 
@@ -791,50 +1013,495 @@ fn action_reward_user(state: &SharedState, client_id: usize, action: ClientActio
         client_id, action.user_id, action.outpost_id,
     );
 
-    // Find client user ID
-    let user_id = match state.clients.client_user_id(client_id) {
+    // Find client user ID, user must be allowed to show outpost code
+    let user_id = match authorize(
+        state,
+        client_id,
+        Permission::Outpost(action.outpost_id),
+        "manually reward user from outpost",
+    ) {
         Some(id) => id,
         None => return,
     };
 
-    // User must have admin role
-    let role_admin = state
-        .config
-        .user(user_id)
-        .map(|u| u.role_admin)
-        .unwrap_or(false);
-    if !role_admin {
-        warn!("Non-game user tried to manually reward user from outpost");
+    // Game must be running, outpost must be within its opening hours
+    if !state.game.running() {
+        warn!("Referee rewarded user while game isn't running");
+        let msg = MsgSendKind::Toast(crate::lang::GAME_NOT_RUNNING.into());
+        send_to_client(state, client_id, &msg.into());
+        return;
+    }
+    if outpost_closed(state, action.outpost_id) {
+        warn!("Referee rewarded user at closed outpost");
+        let msg = MsgSendKind::Toast(crate::lang::OUTPOST_CLOSED.into());
+        send_to_client(state, client_id, &msg.into());
         return;
     }
 
     // Run scan code action
-    let inventory =
+    let before = audit_inventory(state, action.user_id, None);
+    let (inventory, _, capture) =
         match state
             .game
-            .user_scan_code(action.user_id, &state.config, action.outpost_id)
+            .user_scan_code(action.user_id, &state.config, Some(action.outpost_id))
         {
-            Some(inventory) => inventory,
+            Some(result) => result,
             None => {
-                warn!("User scanned same post as last time");
+                warn!("User scanned disabled, cooling down or same post as last time");
                 let msg = MsgSendKind::Toast(crate::lang::SCANNED_SAME_POST_LAST_TIME.into());
                 send_to_client(state, client_id, &msg.into());
                 return;
             }
         };
+    let mut after = audit_inventory(state, action.user_id, None);
+    after["outpost"] = json!(action.outpost_id);
+    state.audit(user_id, "reward_user", Some(action.user_id), before, after);
 
-    // Send user balances update
-    let msg = MsgSendKind::InventoryBalances {
-        money: inventory.money,
-        energy: inventory.energy,
-    };
+    // Send new inventory state, outposts may reward items
+    let msg = MsgSendKind::Inventory(inventory);
     send_to_user(state, Some(client_id), action.user_id, &msg.into());
 
+    if let Some(capture) = capture {
+        send_outpost_capture(state, Some(client_id), action.user_id, capture);
+    }
+
     // Send confirmation to admin
     let msg = MsgSendKind::Toast(crate::lang::USER_REWARDS_GIVEN.into());
     send_to_client(state, client_id, &msg.into());
 }
 
+fn get_outposts(state: &SharedState, client_id: usize) {
+    debug!("Client {} invoked get outposts", client_id);
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "get outposts") {
+        Some(id) => id,
+        None => return,
+    };
+
+    let outposts = state
+        .game
+        .client_outposts(&state.config, crate::auth::now());
+    let msg = MsgSendKind::Outposts(outposts);
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn action_set_outpost_enabled(
+    state: &SharedState,
+    client_id: usize,
+    action: ClientActionSetOutpostEnabled,
+) {
+    debug!(
+        "Client {} invoked set outpost {} enabled: {}",
+        client_id, action.outpost_id, action.enabled
+    );
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(
+        state,
+        client_id,
+        Permission::Admin,
+        "enable or disable outpost",
+    ) {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Enable or disable outpost
+    let before = state.game.outpost_enabled(&state.config, action.outpost_id);
+    if state
+        .game
+        .admin_set_outpost_enabled(user_id, &state.config, action.outpost_id, action.enabled)
+        .is_none()
+    {
+        let msg = MsgSendKind::Toast(crate::lang::OUTPOST_UNKNOWN.into());
+        send_to_client(state, client_id, &msg.into());
+        return;
+    }
+    state.audit(
+        user_id,
+        "set_outpost_enabled",
+        None,
+        json!({ "outpost": action.outpost_id, "enabled": before }),
+        json!({ "outpost": action.outpost_id, "enabled": action.enabled }),
+    );
+
+    // Send updated outpost list to all admins
+    let outposts = state
+        .game
+        .client_outposts(&state.config, crate::auth::now());
+    let msg = MsgSendKind::Outposts(outposts);
+    send_to_all_where(state, Some(client_id), &msg.into(), |user_id| {
+        state.config.user_can(user_id, Permission::Admin)
+    });
+
+    let msg = MsgSendKind::Toast(crate::lang::OUTPOST_UPDATED.into());
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn get_outpost_owners(state: &SharedState, client_id: usize) {
+    debug!("Client {} invoked get outpost owners", client_id);
+
+    // Client must be authenticated
+    if state.clients.client_user_id(client_id).is_none() {
+        return;
+    }
+
+    let msg = MsgSendKind::OutpostOwners(state.game.client_outpost_owners(&state.config));
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn get_trades(state: &SharedState, client_id: usize) {
+    debug!("Client {} invoked get trades", client_id);
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "get trades") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Send pending trades
+    let msg = MsgSendKind::Trades(state.game.user_client_trades(&state.config, user_id));
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn action_trade_offer(state: &SharedState, client_id: usize, action: ClientActionTradeOffer) {
+    debug!(
+        "Client {} invoked trade offer to user {}",
+        client_id, action.to
+    );
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "offer trade") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Game must be running
+    if !state.game.running() {
+        warn!("User offered trade while game isn't running");
+        return;
+    }
+
+    // Make offer, send notification if invalid
+    let (inventory, trade) = match state.game.user_trade_offer(user_id, &state.config, &action) {
+        Some(result) => result,
+        None => {
+            let msg = MsgSendKind::Toast(crate::lang::TRADE_INVALID.into());
+            send_to_client(state, client_id, &msg.into());
+
+            // Broadcast inventory state to reset client state
+            let inventory = state.game.user_client_inventory(&state.config, user_id);
+            if let Some(inventory) = inventory {
+                let msg = MsgSendKind::Inventory(inventory);
+                send_to_user(state, Some(client_id), user_id, &msg.into());
+            }
+
+            return;
+        }
+    };
+
+    // Send inventory, offered items are now held in escrow
+    let msg = MsgSendKind::Inventory(inventory);
+    send_to_user(state, Some(client_id), user_id, &msg.into());
+
+    // Notify both users of new trade
+    let (from, to) = (trade.from, trade.to);
+    let msg = MsgSendKind::Trade(trade).into();
+    send_to_user(state, Some(client_id), from, &msg);
+    send_to_user(state, Some(client_id), to, &msg);
+}
+
+fn action_trade_accept(state: &SharedState, client_id: usize, trade_id: u32) {
+    debug!("Client {} invoked trade accept {}", client_id, trade_id);
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "accept trade") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Game must be running
+    if !state.game.running() {
+        warn!("User accepted trade while game isn't running");
+        return;
+    }
+
+    // Accept trade, send notification on failure
+    let from_id = match state
+        .game
+        .user_trade_accept(user_id, &state.config, trade_id)
+    {
+        Some(id) => id,
+        None => {
+            let msg = MsgSendKind::Toast(crate::lang::TRADE_ACCEPT_FAILED.into());
+            send_to_client(state, client_id, &msg.into());
+            return;
+        }
+    };
+
+    // Notify both users, send their new inventory state
+    let msg = MsgSendKind::TradeClosed {
+        id: trade_id,
+        accepted: true,
+    }
+    .into();
+    for user_id in [from_id, user_id] {
+        send_to_user(state, Some(client_id), user_id, &msg);
+        let inventory = state.game.user_client_inventory(&state.config, user_id);
+        if let Some(inventory) = inventory {
+            let msg = MsgSendKind::Inventory(inventory);
+            send_to_user(state, Some(client_id), user_id, &msg.into());
+        }
+    }
+}
+
+fn action_trade_decline(state: &SharedState, client_id: usize, trade_id: u32) {
+    debug!("Client {} invoked trade decline {}", client_id, trade_id);
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "decline trade") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Decline trade, returning escrow to offering user
+    let (from_id, to_id) = match state
+        .game
+        .user_trade_decline(user_id, &state.config, trade_id)
+    {
+        Some(ids) => ids,
+        None => return,
+    };
+
+    // Notify both users, send offering user its returned items
+    let msg = MsgSendKind::TradeClosed {
+        id: trade_id,
+        accepted: false,
+    }
+    .into();
+    send_to_user(state, Some(client_id), from_id, &msg);
+    send_to_user(state, Some(client_id), to_id, &msg);
+    let inventory = state.game.user_client_inventory(&state.config, from_id);
+    if let Some(inventory) = inventory {
+        let msg = MsgSendKind::Inventory(inventory);
+        send_to_user(state, Some(client_id), from_id, &msg.into());
+    }
+}
+
+fn get_missions(state: &SharedState, client_id: usize) {
+    debug!("Client {} invoked get missions", client_id);
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "get missions") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Get missions
+    let missions = match state.game.user_client_missions(&state.config, user_id) {
+        Some(missions) => missions,
+        None => return,
+    };
+
+    // Send missions
+    let msg = MsgSendKind::Missions(missions);
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn action_submit_mission(state: &SharedState, client_id: usize, mission_id: u32) {
+    debug!("Client {} invoked submit mission {}", client_id, mission_id);
+
+    // Find client user ID, user must play the game
+    let user_id = match authorize(state, client_id, Permission::Play, "submit mission") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Game must be running
+    if !state.game.running() {
+        warn!("User submitted mission while game isn't running");
+        return;
+    }
+
+    // Submit mission, send notification if requirements aren't met
+    let (inventory, _) = match state
+        .game
+        .user_submit_mission(user_id, &state.config, mission_id)
+    {
+        Some(result) => result,
+        None => {
+            let msg = MsgSendKind::Toast(crate::lang::MISSION_REQUIREMENTS_NOT_MET.into());
+            send_to_client(state, client_id, &msg.into());
+            return;
+        }
+    };
+
+    // Send new inventory state, many cells may have changed
+    let msg = MsgSendKind::Inventory(inventory);
+    send_to_user(state, Some(client_id), user_id, &msg.into());
+
+    // Send updated mission list
+    if let Some(missions) = state.game.user_client_missions(&state.config, user_id) {
+        let msg = MsgSendKind::Missions(missions);
+        send_to_user(state, Some(client_id), user_id, &msg.into());
+    }
+
+    let msg = MsgSendKind::Toast(crate::lang::MISSION_COMPLETED.into());
+    send_to_client(state, client_id, &msg.into());
+}
+
+fn get_sessions(
+    server: &SharedServer,
+    state: &SharedState,
+    client_id: usize,
+    filter_user_id: Option<u32>,
+) {
+    debug!("Client {} invoked get sessions", client_id);
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "get sessions") {
+        Some(id) => id,
+        None => return,
+    };
+
+    send_sessions(server, state, client_id, filter_user_id);
+}
+
+fn action_revoke_session(
+    server: &SharedServer,
+    state: &SharedState,
+    client_id: usize,
+    session_id: Option<&str>,
+    revoke_user_id: Option<u32>,
+) {
+    debug!(
+        "Client {} invoked revoke session (session: {:?}, user: {:?})",
+        client_id, session_id, revoke_user_id
+    );
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "revoke session") {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Revoke sessions, only in the game of this admin, audit the user they belonged to
+    let (revoked, target) = match (session_id, revoke_user_id) {
+        (Some(session_id), _) => match server.revoke_session(&state.id, session_id) {
+            Some(target) => (1, Some(target)),
+            None => (0, None),
+        },
+        (None, Some(revoke_user_id)) => (
+            server.revoke_user_sessions(&state.id, revoke_user_id),
+            Some(revoke_user_id),
+        ),
+        (None, None) => (0, None),
+    };
+
+    state.audit(
+        user_id,
+        "revoke_session",
+        target,
+        Value::Null,
+        json!({ "session": session_id, "revoked": revoked }),
+    );
+
+    let msg = MsgSendKind::Toast(format!("Revoked {} session(s)", revoked));
+    send_to_client(state, client_id, &msg.into());
+
+    // Admin may have revoked its own session
+    if state.clients.is_registered(client_id) {
+        send_sessions(server, state, client_id, revoke_user_id);
+    }
+}
+
+fn get_audit(state: &SharedState, client_id: usize, query: AuditQuery) {
+    debug!("Client {} invoked get audit", client_id);
+
+    // Find client user ID, user must be admin
+    let user_id = match authorize(state, client_id, Permission::Admin, "get audit log") {
+        Some(id) => id,
+        None => return,
+    };
+
+    let msg = MsgSendKind::Audit(state.audit.query(&query));
+    send_to_client(state, client_id, &msg.into());
+}
+
+/// Find the user of a client, and check whether it has the given permission.
+///
+/// Returns the user ID, or `None` if the client isn't logged in or lacks the permission, in which
+/// case the attempted action is logged.
+fn authorize(
+    state: &SharedState,
+    client_id: usize,
+    permission: Permission,
+    action: &str,
+) -> Option<u32> {
+    let user_id = state.clients.client_user_id(client_id)?;
+    if !state.config.user_can(user_id, permission) {
+        warn!(
+            "User {} without {:?} permission tried to {}",
+            user_id, permission, action
+        );
+        return None;
+    }
+    Some(user_id)
+}
+
+/// Get user balances, and the item at a cell if given, to record in the audit log.
+fn audit_inventory(state: &SharedState, user_id: u32, cell: Option<u8>) -> Value {
+    if state.config.user(user_id).is_none() {
+        return Value::Null;
+    }
+    let inventory = match state.game.user_client_inventory(&state.config, user_id) {
+        Some(inventory) => inventory,
+        None => return Value::Null,
+    };
+
+    let mut value = json!({"money": inventory.money, "energy": inventory.energy});
+    if let Some(cell) = cell {
+        value["item"] = json!(inventory
+            .grid
+            .items
+            .get(cell as usize)
+            .and_then(|item| item.as_ref())
+            .map(|item| &item.id));
+    }
+    value
+}
+
+/// Whether the given outpost is outside of its opening hours right now.
+fn outpost_closed(state: &SharedState, outpost_id: u32) -> bool {
+    state
+        .config
+        .outposts
+        .outpost(outpost_id)
+        .map(|outpost| !outpost.open_at(crate::auth::now()))
+        .unwrap_or(false)
+}
+
+/// Send list of active sessions in the game to a client, optionally for a single user.
+fn send_sessions(
+    server: &SharedServer,
+    state: &SharedState,
+    client_id: usize,
+    user_id: Option<u32>,
+) {
+    let mut sessions: Vec<ClientUserSession> = server
+        .sessions
+        .list(&state.id, user_id)
+        .iter()
+        .filter(|session| !session.is_expired(crate::auth::now()))
+        .map(|session| ClientUserSession::from_session(&state.config, &state.clients, session))
+        .collect();
+    sessions.sort_by_key(|session| (session.user_id, session.created));
+
+    let msg = MsgSendKind::Sessions(sessions);
+    send_to_client(state, client_id, &msg.into());
+}
+
 /// Send message to all clients.
 ///
 /// Notes:
@@ -865,6 +1532,92 @@ pub fn send_to_all(
     Ok(())
 }
 
+/// Send message to all clients of users matching the given filter.
+///
+/// Notes:
+/// - also sends to the current client as identified by `client_id`.
+/// - returns `Ok` even if the message reaches no client.
+pub fn send_to_all_where<F>(
+    state: &SharedState,
+    client_id: Option<usize>,
+    msg: &MsgSend,
+    filter: F,
+) -> serde_json::Result<()>
+where
+    F: Fn(u32) -> bool,
+{
+    trace!(
+        "WS({}): send msg to filtered clients",
+        client_id.unwrap_or(0),
+    );
+
+    // Serialize
+    let msg = serde_json::to_string(msg)?;
+
+    let clients = state.clients.clients.read().unwrap();
+    for client in clients.iter().filter(|c| filter(c.user_id)) {
+        // Send message, errors happen on disconnect, in which case disconnect logic will be
+        // handled in other task
+        let _ = client.tx.send(Message::text(&msg));
+
+        trace!(
+            "WS({}): - msg queued for client {}",
+            client_id.unwrap_or(0),
+            client.client_id,
+        );
+    }
+
+    Ok(())
+}
+
+/// Send timed round state to a client, or to all clients if `None`.
+///
+/// Includes the final scoreboard once the round has finished.
+pub fn send_round(state: &SharedState, client_id: Option<usize>) {
+    let round = state.game.client_round(&state.config, crate::auth::now());
+    let mut msgs = vec![MsgSendKind::Round(round)];
+    if let Some(scoreboard) = state.game.final_scoreboard() {
+        msgs.push(MsgSendKind::FinalScoreboard(scoreboard));
+    }
+
+    for msg in msgs {
+        match client_id {
+            Some(client_id) => send_to_client(state, client_id, &msg.into()),
+            None => send_to_all(state, None, &msg.into()),
+        };
+    }
+}
+
+/// Send owners of captured outposts to a client, or to all clients if `None`.
+pub fn send_outpost_owners(state: &SharedState, client_id: Option<usize>) {
+    let msg = MsgSendKind::OutpostOwners(state.game.client_outpost_owners(&state.config));
+    match client_id {
+        Some(client_id) => send_to_client(state, client_id, &msg.into()),
+        None => send_to_all(state, None, &msg.into()),
+    };
+}
+
+/// Broadcast an outpost capture by the given user, and notify both the new and previous owner.
+fn send_outpost_capture(
+    state: &SharedState,
+    client_id: Option<usize>,
+    user_id: u32,
+    capture: OutpostCapture,
+) {
+    info!(
+        "User {} captured outpost {} from {:?}",
+        user_id, capture.outpost, capture.previous
+    );
+    send_outpost_owners(state, None);
+
+    let msg = MsgSendKind::Toast(crate::lang::OUTPOST_CAPTURED.into());
+    send_to_user(state, client_id, user_id, &msg.into());
+    if let Some(previous) = capture.previous {
+        let msg = MsgSendKind::Toast(crate::lang::OUTPOST_LOST.into());
+        send_to_user(state, client_id, previous, &msg.into());
+    }
+}
+
 /// Send message to client.
 ///
 /// - returns `Ok` even if the message is never sent
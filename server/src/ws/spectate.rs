@@ -0,0 +1,113 @@
+//! Public spectator leaderboard.
+//!
+//! Unauthenticated displays may connect to spectate a game. They can't send anything, and only
+//! receive the game state, round state and leaderboard, which is pushed at a configured interval.
+
+use futures::{SinkExt, StreamExt, TryFutureExt};
+use tokio::sync::mpsc;
+use tokio::time::{self, Duration};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use warp::ws::{Message, WebSocket};
+
+use crate::auth::{generate_client_id, Spectator};
+use crate::client::{ClientLeaderboardUser, MsgSend, MsgSendKind};
+use crate::config::Config;
+use crate::game::Game;
+use crate::state::SharedState;
+
+/// New spectator connected.
+pub async fn connected(state: SharedState, ws: WebSocket) {
+    let client_id = generate_client_id();
+    info!("WS({}): spectator connect (game: {})", client_id, state.id);
+
+    // Keep flushing spectator message queue to websocket
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut rx = UnboundedReceiverStream::new(rx);
+    tokio::task::spawn(async move {
+        while let Some(message) = rx.next().await {
+            ws_tx
+                .send(message)
+                .unwrap_or_else(|e| {
+                    warn!("WS({}): send error: {}", client_id, e);
+                })
+                .await;
+        }
+    });
+
+    // Register spectator, send current state
+    state
+        .clients
+        .register_spectator(Spectator { client_id, tx });
+    send_to_spectators(&state, Some(client_id));
+
+    // Ignore anything spectators send, until disconnected
+    while let Some(result) = ws_rx.next().await {
+        if let Err(e) = result {
+            warn!("WS({}): error: {}", client_id, e);
+            break;
+        }
+    }
+
+    info!("WS({}): spectator disconnect", client_id);
+    state.clients.unregister_spectator(client_id);
+}
+
+/// Spectator update loop, pushes the leaderboard to all spectators at the configured interval.
+pub(crate) async fn spectate_loop(state: SharedState) {
+    let interval_secs = state.config.spectate.interval_secs.max(1);
+    let mut interval = time::interval(Duration::from_secs(interval_secs));
+
+    loop {
+        interval.tick().await;
+        if state.clients.has_spectators() {
+            send_to_spectators(&state, None);
+        }
+    }
+}
+
+/// Send game state, round state and leaderboard to a spectator, or to all spectators if `None`.
+pub fn send_to_spectators(state: &SharedState, client_id: Option<usize>) {
+    let msgs = vec![
+        MsgSendKind::GameState(state.game.running()),
+        MsgSendKind::Round(state.game.client_round(&state.config, crate::auth::now())),
+        MsgSendKind::Leaderboard(leaderboard(&state.config, &state.game)),
+    ];
+
+    let spectators = state.clients.spectators.read().unwrap();
+    for msg in msgs {
+        let msg = match serde_json::to_string(&MsgSend::from(msg)) {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("Failed to serialize spectator message: {}", err);
+                continue;
+            }
+        };
+        spectators
+            .iter()
+            .filter(|s| client_id.map(|id| s.client_id == id).unwrap_or(true))
+            .for_each(|s| {
+                let _ = s.tx.send(Message::text(&msg));
+            });
+    }
+}
+
+/// Get the leaderboard to show to spectators.
+///
+/// Limited to the top users and anonymized as configured. Frozen when the round finished.
+pub fn leaderboard(config: &Config, game: &Game) -> Vec<ClientLeaderboardUser> {
+    let mut leaderboard = game
+        .final_scoreboard()
+        .unwrap_or_else(|| game.leaderboard(config));
+
+    if let Some(top) = config.spectate.top {
+        leaderboard.truncate(top);
+    }
+    if config.spectate.hide_names {
+        for (i, user) in leaderboard.iter_mut().enumerate() {
+            user.name = format!("#{}", i + 1);
+        }
+    }
+
+    leaderboard
+}